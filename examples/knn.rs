@@ -2,19 +2,20 @@ use chrono::Utc;
 use csv::{ReaderBuilder, StringRecord};
 use example_utils::*;
 use int_data_analysis::*;
-use ndarray::{Array2, Axis};
+use ndarray::{concatenate, Array2, Axis};
 use plotters::coord::types::RangedCoordf64;
 use plotters::prelude::*;
 use std::error::Error;
 use std::fs::create_dir_all;
+use std::sync::Arc;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let clusters = 3;
-    let train = parse_file("data/clusters.csv")?;
+    let train = Arc::new(parse_file("data/clusters.csv")?);
     let test = parse_file("data/clusters-test.csv")?;
-    let class_markers = clusterize_and_predict(clusters, &train);
+    let class_markers = clusterize_and_predict(clusters, &train)?;
 
-    let knn = KNearest::new(3, train.clone(), class_markers);
+    let knn = KNearest::new(3, Arc::clone(&train), class_markers)?;
 
     let now = Utc::now().format("(%H:%M:%S %d.%m.%Y)").to_string();
     create_dir_all("figures/test")?;
@@ -22,16 +23,15 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn parse_file(path: &str) -> Result<Array2<f64>, csv::Error> {
+fn parse_file(path: &str) -> Result<Array2<f64>, Box<dyn Error>> {
     let mut reader = ReaderBuilder::new().has_headers(true).from_path(path)?;
     let records: Vec<StringRecord> = reader.records().map(|r| r.unwrap()).collect();
-    Ok(records_into_array(&records))
+    Ok(records_into_array(&records)?)
 }
 
-fn clusterize_and_predict(clusters: u32, data: &Array2<f64>) -> Vec<usize> {
-    let model = KMeans::default().n_clusters(clusters).fit(data);
-    let row_iter = data.axis_iter(Axis(0));
-    row_iter.map(|p| model.predict(p) + 1).collect()
+fn clusterize_and_predict(clusters: u32, data: &Array2<f64>) -> Result<Vec<usize>, Box<dyn Error>> {
+    let model = KMeans::default().n_clusters(clusters).fit(data)?;
+    Ok(model.labels().to_vec())
 }
 
 pub fn create_plot(
@@ -45,10 +45,9 @@ pub fn create_plot(
     let root = SVGBackend::new(&filepath, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
 
-    let mut temp = train.clone();
-    temp.append(Axis(0), test.view())?;
+    let temp = concatenate(Axis(0), &[train.view(), test.view()])?;
 
-    let ranges = calculate_ranges_2d(&temp).expect("Should not be empty");
+    let ranges = calculate_ranges_2d(&temp)?;
     let mut scatter_ctx = ChartBuilder::on(&root)
         .margin(5)
         .x_label_area_size(30)
@@ -69,8 +68,13 @@ fn plot_data(
     knn: &KNearest,
     style_fn: fn(s: ShapeStyle) -> ShapeStyle,
 ) -> Result<(), Box<dyn Error>> {
-    scatter_ctx.draw_series(data.axis_iter(Axis(0)).map(|row| {
-        let style = style_fn(Palette99::pick(knn.predict(row) - 1).into());
+    let classes = data
+        .rows()
+        .into_iter()
+        .map(|row| knn.predict(row))
+        .collect::<Result<Vec<_>, _>>()?;
+    scatter_ctx.draw_series(data.axis_iter(Axis(0)).zip(classes).map(|(row, class)| {
+        let style = style_fn(Palette99::pick(class).into());
         Circle::new((row[0], row[1]), 3, style)
     }))?;
     Ok(())
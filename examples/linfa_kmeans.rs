@@ -16,7 +16,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let filepath = "data/clusters.csv";
     let mut reader = ReaderBuilder::new().has_headers(true).from_path(filepath)?;
     let records: Vec<StringRecord> = reader.records().map(|r| r.unwrap()).collect();
-    let data = records_into_array(&records);
+    let data = records_into_array(&records)?;
 
     let dataset = DatasetBase::from(data);
     let model = KMeans::params_with_rng(n_clusters, Xoshiro256Plus::seed_from_u64(42))
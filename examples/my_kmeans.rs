@@ -12,10 +12,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         .has_headers(true)
         .from_path("data/clusters.csv")?;
     let records: Vec<StringRecord> = reader.records().map(|r| r.unwrap()).collect();
-    let data = records_into_array(&records);
+    let data = records_into_array(&records)?;
 
     let n_clusters = 3;
-    let model = KMeans::default().n_clusters(n_clusters).fit(&data);
+    let model = KMeans::default().n_clusters(n_clusters).fit(&data)?;
     println!("Result\n{:?}", model.centroids());
 
     let now = Utc::now().format("(%H:%M:%S %d.%m.%Y)").to_string();
@@ -38,7 +38,7 @@ pub fn plot_clusters(
     let root = SVGBackend::new(&filepath, (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
 
-    let ranges = calculate_ranges_2d(data).expect("should not be empty");
+    let ranges = calculate_ranges_2d(data)?;
     let mut scatter_ctx = ChartBuilder::on(&root)
         .margin(5)
         .x_label_area_size(30)
@@ -46,8 +46,9 @@ pub fn plot_clusters(
         .build_cartesian_2d(ranges.0, ranges.1)?;
 
     scatter_ctx.configure_mesh().disable_mesh().draw()?;
-    scatter_ctx.draw_series(data.axis_iter(Axis(0)).map(|row| {
-        let style = Palette99::pick(model.predict(row));
+    let labels = model.predict_many(data)?;
+    scatter_ctx.draw_series(data.axis_iter(Axis(0)).zip(labels).map(|(row, label)| {
+        let style = Palette99::pick(label);
         Circle::new((row[0], row[1]), 3, style.filled())
     }))?;
     root.present()?;
@@ -1,7 +1,7 @@
 use chrono::Utc;
 use csv::{ReaderBuilder, StringRecord};
 use int_data_analysis::kmeans::{KMeans, Model};
-use ndarray::{s, Array2, Axis};
+use ndarray::{concatenate, s, Array2, Axis};
 use plotters::prelude::*;
 use std::collections::HashMap;
 use std::error::Error;
@@ -13,12 +13,16 @@ fn main() -> Result<(), Box<dyn Error>> {
         .has_headers(true)
         .from_path("data/wine-quality.csv")?;
     let records: Vec<StringRecord> = reader.records().map(|r| r.unwrap()).collect();
-    let data = records_into_array(&records);
+    let data = records_into_array(&records)?;
 
     println!("Clusters - Inertia");
     let mut models: HashMap<u32, Model> = HashMap::new();
     for n_clusters in 1..=6 {
-        let model = KMeans::default().n_clusters(n_clusters).fit(&data);
+        let model = KMeans::default()
+            .n_clusters(n_clusters)
+            .n_init(10)
+            .seed(42)
+            .fit(&data)?;
         println!("{n_clusters} - {}", model.inertia());
         models.insert(n_clusters, model);
     }
@@ -68,7 +72,7 @@ fn create_plot(filepath: String, model: &Model, data: &Array2<f64>) -> Result<()
         .x_label_formatter(&|n| String::from(x_labels[*n]))
         .draw()?;
 
-    let norm_data = normalize_centroids(&model.centroids(), data);
+    let norm_data = normalize_centroids(&model.centroids(), data)?;
     for (idx, each) in norm_data.rows().into_iter().enumerate() {
         chart_ctx.draw_series(LineSeries::new(
             each.iter().enumerate().map(|(x, y)| (x, *y)),
@@ -85,12 +89,13 @@ fn create_plot(filepath: String, model: &Model, data: &Array2<f64>) -> Result<()
     Ok(())
 }
 
-fn normalize_centroids(centroids: &Array2<f64>, data: &Array2<f64>) -> Array2<f64> {
-    let mut temp = data.clone();
-    temp.append(Axis(0), centroids.view())
-        .expect("shapes should match");
-    let normalized = normalize_data(&temp);
-    normalized
+fn normalize_centroids(
+    centroids: &Array2<f64>,
+    data: &Array2<f64>,
+) -> Result<Array2<f64>, Box<dyn Error>> {
+    let temp = concatenate(Axis(0), &[data.view(), centroids.view()])?;
+    let normalized = normalize_data(&temp)?;
+    Ok(normalized
         .slice(s![normalized.nrows() - centroids.nrows().., ..])
-        .into_owned()
+        .into_owned())
 }
@@ -2,7 +2,7 @@ use chrono::Utc;
 use csv::{ReaderBuilder, StringRecord};
 use example_utils::*;
 use int_data_analysis::*;
-use ndarray::{s, Array1, Array2, Axis};
+use ndarray::{concatenate, s, Array1, Array2, Axis};
 use plotters::prelude::*;
 use rand::prelude::*;
 use rand_xoshiro::Xoshiro256Plus;
@@ -11,15 +11,17 @@ use std::error::Error;
 use std::fs::create_dir_all;
 use std::io::stdin;
 use std::ops::Range;
+use std::sync::Arc;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let data = parse_file("data/wine-quality.csv")?.to_owned();
-    let (train, mut test) = split_train_test(data, 0.25);
-    let test2 = parse_file("data/wine-test2.csv")?.to_owned();
-    test.append(Axis(0), test2.view())?;
-
-    let class_markers = clusterize_and_predict(&train);
-    let knn = KNearest::new(11, train.clone(), class_markers);
+    let data = parse_file("data/wine-quality.csv")?;
+    let (train, test) = split_train_test(&data, 0.25)?;
+    let test2 = parse_file("data/wine-test2.csv")?;
+    let test = concatenate(Axis(0), &[test, test2.view()])?;
+
+    let train = Arc::new(train.to_owned());
+    let class_markers = clusterize_and_predict(&train)?;
+    let knn = KNearest::new(11, Arc::clone(&train), class_markers)?;
     let now = Utc::now().format("(%H:%M:%S %d.%m.%Y)").to_string();
     create_dir_all("figures/wine")?;
     create_plot(format!("figures/wine/knn {}.svg", now), &train, &test, &knn)?;
@@ -41,7 +43,8 @@ fn predict_interactive(knn: &KNearest) -> Result<(), Box<dyn Error>> {
 
         match predict_from_input(knn, &args) {
             Err(e) => println!("Error: {}", e),
-            Ok(c) => println!("Class: {}", c),
+            // KMeans labels start at 0, classes are shown from 1 like in the plot legend
+            Ok(c) => println!("Class: {}", c + 1),
         }
     }
 
@@ -53,26 +56,29 @@ fn predict_from_input(knn: &KNearest, args: &Vec<&str>) -> Result<usize, Box<dyn
     for each in args {
         v.push(each.parse::<f64>()?);
     }
-    Ok(knn.predict(Array1::from_vec(v).view()))
+    Ok(knn.predict(Array1::from_vec(v).view())?)
 }
 
-fn parse_file(path: &str) -> Result<Array2<f64>, csv::Error> {
+fn parse_file(path: &str) -> Result<Array2<f64>, Box<dyn Error>> {
     let mut reader = ReaderBuilder::new().has_headers(true).from_path(path)?;
     let mut records: Vec<StringRecord> = reader.records().map(|r| r.unwrap()).collect();
     records.shuffle(&mut Xoshiro256Plus::seed_from_u64(42));
-    Ok(records_into_array(&records))
+    Ok(records_into_array(&records)?)
 }
 
-fn clusterize_and_predict(data: &Array2<f64>) -> Vec<usize> {
+fn clusterize_and_predict(data: &Array2<f64>) -> Result<Vec<usize>, Box<dyn Error>> {
     let mut models: HashMap<u32, Model> = HashMap::new();
     for n_clusters in 1..=6 {
-        let model = KMeans::default().n_clusters(n_clusters).fit(data);
+        let model = KMeans::default()
+            .n_clusters(n_clusters)
+            .n_init(10)
+            .seed(42)
+            .fit(data)?;
         models.insert(n_clusters, model);
     }
     let best_model = models.get(&4).unwrap();
 
-    let row_iter = data.axis_iter(Axis(0));
-    row_iter.map(|p| best_model.predict(p) + 1).collect()
+    Ok(best_model.labels().to_vec())
 }
 
 pub fn create_plot(
@@ -81,13 +87,30 @@ pub fn create_plot(
     test: &Array2<f64>,
     knn: &KNearest,
 ) -> Result<(), Box<dyn Error>> {
-    let labels = vec!["Alcohol", "Malic Acid", "Ash"];
+    let labels = ["Alcohol", "Malic Acid", "Ash"];
+
+    // Series and colours follow the position of a label in `classes`, since the labels
+    // themselves need not be `0..nclasses`
+    let class_position = |row| -> Result<usize, Box<dyn Error>> {
+        let class = knn.predict(row)?;
+        Ok(knn
+            .classes()
+            .iter()
+            .position(|c| *c == class)
+            .expect("Predicted labels are training labels"))
+    };
 
     let mut train_series = vec![vec![]; knn.nclasses()];
     for each in train.rows() {
-        train_series[knn.predict(each) - 1].push(each);
+        train_series[class_position(each)?].push(each);
     }
 
+    let test_classes = test
+        .rows()
+        .into_iter()
+        .map(class_position)
+        .collect::<Result<Vec<_>, _>>()?;
+
     let root = SVGBackend::new(&filepath, (2400, 1800)).into_drawing_area();
     root.fill(&WHITE)?;
     let drawing_areas = root.split_evenly((3, 3));
@@ -104,9 +127,9 @@ pub fn create_plot(
         r_test.push_column(test.slice(s![.., x]))?;
         r_test.push_column(test.slice(s![.., y]))?;
 
-        let ranges = ranges(&r_train, &r_test);
+        let ranges = ranges(&r_train, &r_test)?;
         let caption = format!("{} x {}", labels[x], labels[y]);
-        let mut cc = ChartBuilder::on(&each)
+        let mut cc = ChartBuilder::on(each)
             .caption(caption, ("sans-serif", 20).into_font())
             .margin(10)
             .x_label_area_size(30)
@@ -122,12 +145,13 @@ pub fn create_plot(
                 color.stroke_width(1),
                 &Circle::new,
             ))?
-            .label(format!("Class {}", idx + 1))
+            .label(format!("Class {}", knn.classes()[idx] + 1))
             .legend(move |c| Circle::new(c, 4, color.filled()));
         }
 
-        cc.draw_series(test.axis_iter(Axis(0)).map(|row| {
-            let color = Palette99::pick(knn.predict(row) - 1);
+        let test_points = test.axis_iter(Axis(0)).zip(&test_classes);
+        cc.draw_series(test_points.map(|(row, class)| {
+            let color = Palette99::pick(*class);
             Circle::new((row[x], row[y]), 3, color.filled())
         }))?;
 
@@ -141,8 +165,10 @@ pub fn create_plot(
     Ok(())
 }
 
-fn ranges(train: &Array2<f64>, test: &Array2<f64>) -> (Range<f64>, Range<f64>) {
-    let mut temp = train.clone();
-    temp.append(Axis(0), test.view()).expect("shapes match");
-    calculate_ranges_2d(&temp).expect("should not be empty")
+fn ranges(
+    train: &Array2<f64>,
+    test: &Array2<f64>,
+) -> Result<(Range<f64>, Range<f64>), Box<dyn Error>> {
+    let temp = concatenate(Axis(0), &[train.view(), test.view()])?;
+    Ok(calculate_ranges_2d(&temp)?)
 }
@@ -0,0 +1,392 @@
+use crate::error::{Error, Result};
+use crate::euclidean_distance;
+use crate::float::Float;
+use ndarray::{azip, Array1, Array2, ArrayBase, ArrayView1, Data, Ix2};
+
+/// Dissimilarity between two points of the same dimension.
+pub trait Distance<F: Float = f64>: Send + Sync {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F;
+
+    /// Lower bound of the distance between points whose values in column `axis` differ by
+    /// `diff`. `None` when the metric can't be bounded per column, which rules out KD-trees.
+    fn axis_bound(&self, _axis: usize, _diff: F) -> Option<F> {
+        None
+    }
+
+    /// Whether `d(a, c) <= d(a, b) + d(b, c)` always holds, which ball and VP trees rely on.
+    fn satisfies_triangle_inequality(&self) -> bool {
+        false
+    }
+
+    /// Fails if the metric holds a parameter per column and was built for a different
+    /// number of columns than `ncols`.
+    fn check_columns(&self, _ncols: usize) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Euclidean;
+
+impl<F: Float> Distance<F> for Euclidean {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        euclidean_distance(point1, point2)
+    }
+
+    fn axis_bound(&self, _axis: usize, diff: F) -> Option<F> {
+        Some(diff.abs())
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+}
+
+/// Same ordering as `Euclidean` without the square root. Not a metric: it breaks the triangle
+/// inequality.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SquaredEuclidean;
+
+impl<F: Float> Distance<F> for SquaredEuclidean {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        point1
+            .iter()
+            .zip(point2)
+            .map(|(a, b)| (*a - *b).powi(2))
+            .sum()
+    }
+
+    fn axis_bound(&self, _axis: usize, diff: F) -> Option<F> {
+        Some(diff.powi(2))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Manhattan;
+
+impl<F: Float> Distance<F> for Manhattan {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        point1
+            .iter()
+            .zip(point2)
+            .map(|(a, b)| (*a - *b).abs())
+            .sum()
+    }
+
+    fn axis_bound(&self, _axis: usize, diff: F) -> Option<F> {
+        Some(diff.abs())
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Chebyshev;
+
+impl<F: Float> Distance<F> for Chebyshev {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        point1
+            .iter()
+            .zip(point2)
+            .map(|(a, b)| (*a - *b).abs())
+            .fold(F::zero(), F::max)
+    }
+
+    fn axis_bound(&self, _axis: usize, diff: F) -> Option<F> {
+        Some(diff.abs())
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+}
+
+/// Minkowski distance of order `p`; `p = 1` is `Manhattan` and `p = 2` is `Euclidean`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Minkowski(f64);
+
+impl Minkowski {
+    /// Fails unless `p` is positive and finite.
+    pub fn new(p: f64) -> Result<Self> {
+        if !(p > 0.0 && p.is_finite()) {
+            return Err(Error::InvalidParameter(format!(
+                "Minkowski order must be positive and finite, found {}",
+                p
+            )));
+        }
+        Ok(Minkowski(p))
+    }
+
+    pub fn p(&self) -> f64 {
+        self.0
+    }
+}
+
+impl<F: Float> Distance<F> for Minkowski {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        let p = F::cast(self.0);
+        let sum: F = point1
+            .iter()
+            .zip(point2)
+            .map(|(a, b)| (*a - *b).abs().powf(p))
+            .sum();
+        sum.powf(p.recip())
+    }
+
+    fn axis_bound(&self, _axis: usize, diff: F) -> Option<F> {
+        Some(diff.abs())
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        self.0 >= 1.0
+    }
+}
+
+/// `1 - cos(angle)` between the two points, in `[0, 2]`. A zero vector is treated as
+/// orthogonal to everything. Not a metric; use `Angular` with tree indexes, it ranks
+/// neighbours the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cosine;
+
+impl<F: Float> Distance<F> for Cosine {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        let norms = point1.dot(&point1).sqrt() * point2.dot(&point2).sqrt();
+        if norms == F::zero() {
+            return F::one();
+        }
+        F::one() - point1.dot(&point2) / norms
+    }
+}
+
+/// Angle between the two points divided by `pi`, in `[0, 1]`. A metric that orders
+/// neighbours exactly like `Cosine`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Angular;
+
+impl<F: Float> Distance<F> for Angular {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        let cos = F::one() - Cosine.distance(point1, point2);
+        cos.max(-F::one()).min(F::one()).acos() / F::cast(std::f64::consts::PI)
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+}
+
+/// Euclidean distance with a non-negative weight per column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedEuclidean<F = f64>(Array1<F>);
+
+impl<F: Float> WeightedEuclidean<F> {
+    /// Fails unless every weight is finite and non-negative. Models using the metric check
+    /// that there is one weight per column.
+    pub fn new(weights: Array1<F>) -> Result<Self> {
+        if let Some(weight) = weights
+            .iter()
+            .find(|w| !(w.is_finite() && **w >= F::zero()))
+        {
+            return Err(Error::InvalidParameter(format!(
+                "weights must be finite and non-negative, found {:?}",
+                weight
+            )));
+        }
+        Ok(WeightedEuclidean(weights))
+    }
+
+    pub fn weights(&self) -> &Array1<F> {
+        &self.0
+    }
+}
+
+impl<F: Float> Distance<F> for WeightedEuclidean<F> {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        let sum: F = point1
+            .iter()
+            .zip(point2)
+            .zip(&self.0)
+            .map(|((a, b), w)| *w * (*a - *b).powi(2))
+            .sum();
+        sum.sqrt()
+    }
+
+    fn axis_bound(&self, axis: usize, diff: F) -> Option<F> {
+        Some(self.0[axis].sqrt() * diff.abs())
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+
+    fn check_columns(&self, ncols: usize) -> Result<()> {
+        if self.0.len() != ncols {
+            return Err(Error::InvalidParameter(format!(
+                "{} weights for {} columns",
+                self.0.len(),
+                ncols
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Euclidean distance after decorrelating and rescaling the columns with an inverse
+/// covariance matrix, so strongly correlated columns are not counted twice.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mahalanobis<F = f64> {
+    inverse_covariance: Array2<F>,
+}
+
+impl<F: Float> Mahalanobis<F> {
+    pub fn new(inverse_covariance: Array2<F>) -> Self {
+        Mahalanobis { inverse_covariance }
+    }
+
+    /// Estimates the covariance of `data` (one sample per row) and inverts it. `shrinkage`
+    /// in `[0, 1]` blends the estimate towards a scaled identity matrix, which keeps it
+    /// invertible when there are few samples or linearly dependent columns. The estimate is
+    /// computed in `f64` whatever the type of `data`. Fails with `Error::InvalidFraction` if
+    /// `shrinkage` is outside `[0, 1]`, since the result wouldn't be a metric.
+    pub fn fit<S: Data<Elem = F>>(data: &ArrayBase<S, Ix2>, shrinkage: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&shrinkage) {
+            return Err(Error::InvalidFraction(shrinkage));
+        }
+        let covariance = shrunk_covariance(data, shrinkage);
+        let inverse_covariance = invert(&covariance).ok_or(Error::SingularMatrix)?;
+        Ok(Mahalanobis {
+            inverse_covariance: inverse_covariance.mapv(F::cast),
+        })
+    }
+
+    pub fn inverse_covariance(&self) -> &Array2<F> {
+        &self.inverse_covariance
+    }
+}
+
+impl<F: Float> Distance<F> for Mahalanobis<F> {
+    fn distance(&self, point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+        let diff = &point1 - &point2;
+        diff.dot(&self.inverse_covariance.dot(&diff))
+            .max(F::zero())
+            .sqrt()
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+
+    fn check_columns(&self, ncols: usize) -> Result<()> {
+        if self.inverse_covariance.dim() != (ncols, ncols) {
+            return Err(Error::InvalidParameter(format!(
+                "{:?} inverse covariance for {} columns",
+                self.inverse_covariance.dim(),
+                ncols
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates the mean and covariance row by row in `f64`, so `data` is never copied.
+fn shrunk_covariance<F: Float, S: Data<Elem = F>>(
+    data: &ArrayBase<S, Ix2>,
+    shrinkage: f64,
+) -> Array2<f64> {
+    let n_features = data.ncols();
+    let to_f64 = |e: &F| e.to_f64().expect("Float converts to f64");
+    let mut covariance = Array2::zeros((n_features, n_features));
+    if data.nrows() > 1 {
+        let mut mean = Array1::<f64>::zeros(n_features);
+        for row in data.rows() {
+            mean.zip_mut_with(&row, |m, e| *m += to_f64(e));
+        }
+        mean /= data.nrows() as f64;
+
+        let mut centered = Array1::zeros(n_features);
+        for row in data.rows() {
+            azip!((c in &mut centered, e in &row, m in &mean) *c = to_f64(e) - m);
+            for a in 0..n_features {
+                for b in a..n_features {
+                    covariance[[a, b]] += centered[a] * centered[b];
+                }
+            }
+        }
+        for a in 0..n_features {
+            for b in 0..a {
+                covariance[[a, b]] = covariance[[b, a]];
+            }
+        }
+        covariance /= (data.nrows() - 1) as f64;
+    }
+
+    let mean_variance = covariance.diag().sum() / n_features as f64;
+    covariance *= 1.0 - shrinkage;
+    for i in 0..n_features {
+        covariance[[i, i]] += shrinkage * mean_variance;
+    }
+    covariance
+}
+
+/// Gauss-Jordan elimination with partial pivoting. Returns `None` for singular matrices.
+fn invert(matrix: &Array2<f64>) -> Option<Array2<f64>> {
+    let n = matrix.nrows();
+    let mut left = matrix.clone();
+    let mut right = Array2::eye(n);
+    let scale = matrix.iter().fold(0.0, |acc: f64, e| acc.max(e.abs()));
+
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|a, b| left[[*a, col]].abs().total_cmp(&left[[*b, col]].abs()))?;
+        if left[[pivot, col]].abs() <= scale * 1e-12 {
+            return None;
+        }
+        for j in 0..n {
+            left.swap([col, j], [pivot, j]);
+            right.swap([col, j], [pivot, j]);
+        }
+
+        let divisor = left[[col, col]];
+        left.row_mut(col).mapv_inplace(|e| e / divisor);
+        right.row_mut(col).mapv_inplace(|e| e / divisor);
+        for row in 0..n {
+            let factor = left[[row, col]];
+            if row == col || factor == 0.0 {
+                continue;
+            }
+            let left_col = left.row(col).to_owned();
+            let right_col = right.row(col).to_owned();
+            left.row_mut(row).scaled_add(-factor, &left_col);
+            right.row_mut(row).scaled_add(-factor, &right_col);
+        }
+    }
+    Some(right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn parameters_are_validated() {
+        assert!(Minkowski::new(3.0).is_ok());
+        for p in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(Minkowski::new(p).is_err(), "{}", p);
+        }
+        assert!(WeightedEuclidean::new(array![0.0, 2.0]).is_ok());
+        assert!(WeightedEuclidean::new(array![1.0, -0.5]).is_err());
+        assert!(WeightedEuclidean::new(array![1.0, f64::NAN]).is_err());
+    }
+
+    #[test]
+    fn weights_must_match_the_columns() {
+        let metric = WeightedEuclidean::new(array![1.0, 2.0]).unwrap();
+        assert!(metric.check_columns(2).is_ok());
+        assert!(matches!(
+            metric.check_columns(3),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::float::Float;
+use ndarray::{ArrayBase, Data, Ix2};
+use std::fmt::{Display, Formatter};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// Centroid with the given index received no points.
+    EmptyCluster(usize),
+    /// Array has a different shape (rows, columns) than required.
+    ShapeMismatch {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// List has a different length than the number of rows it describes.
+    LengthMismatch { expected: usize, found: usize },
+    /// Dataset has no rows.
+    EmptyDataset,
+    /// Number of clusters is zero or larger than the number of samples.
+    InvalidClusters { n_clusters: usize, n_samples: usize },
+    /// Fraction has to lie between `0.0` and `1.0`.
+    InvalidFraction(f64),
+    /// Text cell at the given row and column isn't a number.
+    Parse {
+        row: usize,
+        column: usize,
+        value: String,
+    },
+    /// Value at the given row and column is NaN.
+    NanValue { row: usize, column: usize },
+    /// Number of neighbours is zero or larger than the number of training rows.
+    InvalidK { k: usize, n_samples: usize },
+    /// Matrix that has to be inverted is singular.
+    SingularMatrix,
+    /// Chosen metric can't be used with the requested neighbour index.
+    UnsupportedMetric,
+    /// Neighbour index can't be saved, or loaded bytes don't describe an index of the
+    /// training data.
+    InvalidIndex,
+    /// Hyperparameter has an unusable value; holds the description of the problem.
+    InvalidParameter(String),
+    /// Reading or writing failed; holds the description of the I/O error.
+    Io(String),
+    /// linfa reported an error; holds its description.
+    #[cfg(feature = "linfa")]
+    Linfa(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::EmptyCluster(index) => write!(f, "cluster {} received no points", index),
+            Error::ShapeMismatch { expected, found } => {
+                write!(f, "expected shape {:?}, found {:?}", expected, found)
+            }
+            Error::LengthMismatch { expected, found } => {
+                write!(f, "expected {} values, found {}", expected, found)
+            }
+            Error::EmptyDataset => write!(f, "dataset has no rows"),
+            Error::InvalidClusters {
+                n_clusters,
+                n_samples,
+            } => write!(
+                f,
+                "number of clusters must be between 1 and {}, found {}",
+                n_samples, n_clusters
+            ),
+            Error::InvalidFraction(value) => {
+                write!(f, "fraction must be between 0 and 1, found {}", value)
+            }
+            Error::Parse { row, column, value } => write!(
+                f,
+                "value {:?} at row {}, column {} is not a number",
+                value, row, column
+            ),
+            Error::NanValue { row, column } => {
+                write!(f, "value at row {}, column {} is NaN", row, column)
+            }
+            Error::InvalidK { k, n_samples } => {
+                write!(f, "k must be between 1 and {}, found {}", n_samples, k)
+            }
+            Error::SingularMatrix => write!(f, "matrix is singular, try a positive shrinkage"),
+            Error::UnsupportedMetric => write!(f, "metric is not supported by this index"),
+            Error::InvalidIndex => write!(f, "index is invalid or does not match the data"),
+            Error::InvalidParameter(message) => write!(f, "invalid parameter: {}", message),
+            Error::Io(message) => write!(f, "I/O error: {}", message),
+            #[cfg(feature = "linfa")]
+            Error::Linfa(message) => write!(f, "linfa error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error.to_string())
+    }
+}
+
+#[cfg(feature = "linfa")]
+impl From<linfa::Error> for Error {
+    fn from(error: linfa::Error) -> Self {
+        Error::Linfa(error.to_string())
+    }
+}
+
+/// Fails with `Error::NanValue` at the first NaN in `data`.
+pub(crate) fn check_nan<F: Float, S: Data<Elem = F>>(data: &ArrayBase<S, Ix2>) -> Result<()> {
+    match data.indexed_iter().find(|(_, value)| value.is_nan()) {
+        Some(((row, column), _)) => Err(Error::NanValue { row, column }),
+        None => Ok(()),
+    }
+}
@@ -1,52 +1,82 @@
+use crate::error::{check_nan, Error, Result};
+use crate::float::Float;
+use crate::preprocessing::MinMaxScaler;
+use crate::traits::FitTransform;
 use csv::StringRecord;
-use ndarray::{s, Array1, Array2};
+use ndarray::{Array2, ArrayBase, ArrayView2, Axis, Data, Ix2};
 use std::ops::Range;
 
-pub fn records_into_array(records: &Vec<StringRecord>) -> Array2<f64> {
-    let shape = (records.len(), records[0].len());
-    let vec: Vec<f64> = records
-        .iter()
-        .flat_map(|rec| rec.iter().map(|str| str.parse::<f64>().unwrap()))
-        .collect();
-    Array2::from_shape_vec(shape, vec).unwrap()
+/// Parses every cell of `records` as a number. Fails on an empty or ragged input and on cells
+/// that aren't numbers.
+pub fn records_into_array<F: Float>(records: &[StringRecord]) -> Result<Array2<F>> {
+    let ncols = records.first().ok_or(Error::EmptyDataset)?.len();
+    let mut vec = Vec::with_capacity(records.len() * ncols);
+    for (row, rec) in records.iter().enumerate() {
+        if rec.len() != ncols {
+            return Err(Error::ShapeMismatch {
+                expected: (1, ncols),
+                found: (1, rec.len()),
+            });
+        }
+        for (column, str) in rec.iter().enumerate() {
+            let value = str.parse::<F>().map_err(|_| Error::Parse {
+                row,
+                column,
+                value: str.to_owned(),
+            })?;
+            vec.push(value);
+        }
+    }
+    Ok(Array2::from_shape_vec((records.len(), ncols), vec).expect("Shape matches"))
 }
 
-pub fn calculate_ranges_2d(data: &Array2<f64>) -> Option<(Range<f64>, Range<f64>)> {
-    let cmp_fn = |a: &&f64, b: &&f64| a.partial_cmp(b).expect("Should be comparable");
+/// Plot ranges of the first two columns. Fails on an empty input, fewer than two columns and
+/// NaN values.
+pub fn calculate_ranges_2d<F: Float, S: Data<Elem = F>>(
+    data: &ArrayBase<S, Ix2>,
+) -> Result<(Range<F>, Range<F>)> {
+    if data.nrows() == 0 {
+        return Err(Error::EmptyDataset);
+    }
+    if data.ncols() < 2 {
+        return Err(Error::ShapeMismatch {
+            expected: (data.nrows(), 2),
+            found: data.dim(),
+        });
+    }
+    check_nan(data)?;
+    let cmp_fn = |a: &&F, b: &&F| a.total_cmp(b);
 
     let x_col = data.column(0);
-    let x_min = x_col.iter().min_by(cmp_fn)?.floor();
-    let x_max = x_col.iter().max_by(cmp_fn)?.ceil();
+    let x_min = x_col.iter().min_by(cmp_fn).unwrap().floor();
+    let x_max = x_col.iter().max_by(cmp_fn).unwrap().ceil();
 
     let y_col = data.column(1);
-    let y_min = y_col.iter().min_by(cmp_fn)?.floor();
-    let y_max = y_col.iter().max_by(cmp_fn)?.ceil();
+    let y_min = y_col.iter().min_by(cmp_fn).unwrap().floor();
+    let y_max = y_col.iter().max_by(cmp_fn).unwrap().ceil();
 
-    let x_ofs = (x_max - x_min) / 20.0;
-    let y_ofs = (y_max - y_min) / 20.0;
+    let x_ofs = (x_max - x_min) / F::cast(20.0);
+    let y_ofs = (y_max - y_min) / F::cast(20.0);
     let ofs = x_ofs.min(y_ofs);
-    Some((x_min - ofs..x_max + ofs, y_min - ofs..y_max + ofs))
+    Ok((x_min - ofs..x_max + ofs, y_min - ofs..y_max + ofs))
 }
 
-pub fn normalize_data(data: &Array2<f64>) -> Array2<f64> {
-    let mut array: Array2<f64> = Array2::zeros((data.nrows(), 0));
-    for c in data.columns() {
-        let min = c.iter().min_by(|a, b| a.total_cmp(b)).unwrap().to_owned();
-        let max = c.iter().max_by(|a, b| a.total_cmp(b)).unwrap().to_owned();
-        let v: Vec<f64> = c
-            .into_iter()
-            .map(|each| (each - min) / (max - min))
-            .collect();
-        array
-            .push_column(Array1::from(v).view())
-            .expect("Column length match");
-    }
-    array
+/// Scales every column to `0.0..=1.0`; constant columns become `0.0`. Fails on an empty input
+/// and NaN values. See `MinMaxScaler` to apply the same scaling to other data.
+pub fn normalize_data<F: Float, S: Data<Elem = F>>(data: &ArrayBase<S, Ix2>) -> Result<Array2<F>> {
+    let (_, normalized) = MinMaxScaler.fit_transform(data.view())?;
+    Ok(normalized)
 }
 
-pub fn split_train_test(data: Array2<f64>, test_fraction: f64) -> (Array2<f64>, Array2<f64>) {
+/// Splits off the last `test_fraction` of the rows as views into `data`, so nothing is
+/// copied. Fails unless the fraction lies between `0.0` and `1.0`.
+pub fn split_train_test<F: Float, S: Data<Elem = F>>(
+    data: &ArrayBase<S, Ix2>,
+    test_fraction: f64,
+) -> Result<(ArrayView2<'_, F>, ArrayView2<'_, F>)> {
+    if !(0.0..=1.0).contains(&test_fraction) {
+        return Err(Error::InvalidFraction(test_fraction));
+    }
     let train_len = ((1.0 - test_fraction) * data.nrows() as f64).ceil() as usize;
-    let train = data.slice(s![..train_len, ..]).to_owned();
-    let test = data.slice(s![train_len.., ..]).to_owned();
-    (train, test)
+    Ok(data.view().split_at(Axis(0), train_len))
 }
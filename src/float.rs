@@ -0,0 +1,54 @@
+use approx::AbsDiffEq;
+use ndarray::ScalarOperand;
+use num_traits::FromPrimitive;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
+use std::str::FromStr;
+
+/// Floating point type of datasets, centroids and distances: `f32` or `f64`.
+pub trait Float:
+    num_traits::Float
+    + FromPrimitive
+    + ScalarOperand
+    + AbsDiffEq<Epsilon = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + Sum
+    + Debug
+    + Display
+    + FromStr
+    + Default
+    + Send
+    + Sync
+    + 'static
+{
+    /// `value` rounded to the nearest `Self`.
+    fn cast(value: f64) -> Self;
+
+    /// Same as `f64::total_cmp`: orders NaN after every number.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl Float for f32 {
+    fn cast(value: f64) -> Self {
+        value as f32
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+}
+
+impl Float for f64 {
+    fn cast(value: f64) -> Self {
+        value
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
@@ -1,14 +1,50 @@
-use super::euclidean_distance;
+use crate::distance::{Distance, Euclidean};
+use crate::error::{check_nan, Error, Result};
+use crate::euclidean_distance;
+use crate::float::Float;
+use crate::parallel::map_indexes;
+use crate::traits::{Fit, Predict, Transform};
 use approx::abs_diff_eq;
-use ndarray::{Array2, ArrayView1, Axis};
-use ndarray_rand::rand_distr::num_traits::Float;
-use rand::{thread_rng, Rng};
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix2};
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256Plus;
 use std::fmt::Debug;
 
+pub use algorithm::Algorithm;
+pub use init::Init;
+#[cfg(feature = "linfa")]
+pub use linfa::KMeansParams;
+pub use minibatch::{LearningRate, MiniBatchKMeans};
+
+mod algorithm;
+mod init;
+#[cfg(feature = "linfa")]
+mod linfa;
+mod minibatch;
+
+/// What `fit` does with a centroid that received no points during an iteration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmptyCluster {
+    /// Move the centroid to the point farthest from its own centroid.
+    #[default]
+    FarthestPoint,
+    /// Move the centroid to the farthest point of the cluster with the largest sum of squares.
+    LargestError,
+    /// Leave the centroid where it was.
+    KeepPrevious,
+    /// Abort `fit` with `Error::EmptyCluster`.
+    Error,
+}
+
 pub struct KMeans {
     n_clusters: u32,
     tolerance: f64,
     max_n_iterations: u32,
+    n_init: u32,
+    init: Init,
+    algorithm: Algorithm,
+    empty_cluster: EmptyCluster,
+    seed: Option<u64>,
 }
 
 impl Default for KMeans {
@@ -23,6 +59,11 @@ impl KMeans {
             n_clusters,
             tolerance,
             max_n_iterations,
+            n_init: 1,
+            init: Init::default(),
+            algorithm: Algorithm::default(),
+            empty_cluster: EmptyCluster::default(),
+            seed: None,
         }
     }
 
@@ -41,63 +82,170 @@ impl KMeans {
         self
     }
 
-    pub fn fit(&self, dataset: &Array2<f64>) -> Model {
-        let mut centroids = self.plus_plus_init(dataset);
-        let mut inertia = f64::MAX;
-        for _ in 0..self.max_n_iterations {
-            let mut run_inertia = 0.0;
+    /// Number of independent runs; `fit` returns the one with the lowest inertia.
+    pub fn n_init(&mut self, value: u32) -> &mut Self {
+        self.n_init = value.max(1);
+        self
+    }
+
+    pub fn init(&mut self, value: Init) -> &mut Self {
+        self.init = value;
+        self
+    }
+
+    pub fn algorithm(&mut self, value: Algorithm) -> &mut Self {
+        self.algorithm = value;
+        self
+    }
+
+    pub fn empty_cluster(&mut self, value: EmptyCluster) -> &mut Self {
+        self.empty_cluster = value;
+        self
+    }
+
+    /// Makes `fit` deterministic: the same seed and dataset always yield the same `Model`.
+    pub fn seed(&mut self, value: u64) -> &mut Self {
+        self.seed = Some(value);
+        self
+    }
+
+    /// Fails on an empty dataset, NaN values and `n_clusters` outside `1..=nrows`.
+    pub fn fit<F: Float, S: Data<Elem = F>>(
+        &self,
+        dataset: &ArrayBase<S, Ix2>,
+    ) -> Result<Model<F>> {
+        match self.seed {
+            Some(seed) => self.fit_with_rng(dataset, &mut Xoshiro256Plus::seed_from_u64(seed)),
+            None => self.fit_with_rng(dataset, &mut thread_rng()),
+        }
+    }
+
+    /// Fits a model and returns the cluster of every sample in `dataset`.
+    pub fn fit_predict<F: Float, S: Data<Elem = F>>(
+        &self,
+        dataset: &ArrayBase<S, Ix2>,
+    ) -> Result<Array1<usize>> {
+        Ok(self.fit(dataset)?.labels)
+    }
+
+    /// Same as `fit`, but all randomness is drawn from `rng` (the configured seed is ignored).
+    pub fn fit_with_rng<F: Float, S: Data<Elem = F>, R: Rng + ?Sized>(
+        &self,
+        dataset: &ArrayBase<S, Ix2>,
+        rng: &mut R,
+    ) -> Result<Model<F>> {
+        let dataset = dataset.view();
+        check_dataset(dataset, self.n_clusters as usize)?;
+        let mut best = self.fit_once(dataset, rng)?;
+        let mut run_inertias = vec![best.inertia];
+        for _ in 1..self.n_init {
+            let model = self.fit_once(dataset, rng)?;
+            run_inertias.push(model.inertia);
+            if model.inertia < best.inertia {
+                best = model;
+            }
+        }
+        best.run_inertias = run_inertias;
+        Ok(best)
+    }
+
+    fn fit_once<F: Float, R: Rng + ?Sized>(
+        &self,
+        dataset: ArrayView2<F>,
+        rng: &mut R,
+    ) -> Result<Model<F>> {
+        let mut centroids = self
+            .init
+            .centroids(dataset, self.n_clusters as usize, rng)?;
+        let mut assignment = self
+            .algorithm
+            .assignment(dataset.nrows(), centroids.nrows());
+        let mut converged = false;
+        let mut n_iterations = 0;
+        while n_iterations < self.max_n_iterations {
+            n_iterations += 1;
             let previous_centroids = centroids.clone();
             let mut clustered_data_indexes = self.init_clustered_data_index_vec();
 
-            for ei in 0..dataset.nrows() {
-                let closest_centroid = get_closest_centroid(dataset.row(ei), &centroids);
-                run_inertia += closest_centroid.1.powi(2);
-                clustered_data_indexes[closest_centroid.0].push(ei);
+            let (labels, mut distances) = assignment.assign(dataset, &centroids);
+            for (ei, ci) in labels.iter().enumerate() {
+                clustered_data_indexes[*ci].push(ei);
             }
 
-            for ci in 0..centroids.nrows() {
-                let mut array: Array2<f64> = Array2::zeros((0, centroids.ncols()));
-                for ei in &clustered_data_indexes[ci] {
-                    array.push_row(dataset.row(*ei)).unwrap();
+            let new_centroids = map_indexes(clustered_data_indexes.len(), |ci| {
+                dataset
+                    .select(Axis(0), &clustered_data_indexes[ci])
+                    .mean_axis(Axis(0))
+            });
+            let mut empty_clusters = Vec::new();
+            for (ci, new_centroid) in new_centroids.into_iter().enumerate() {
+                match new_centroid {
+                    Some(new_centroid) => centroids.row_mut(ci).assign(&new_centroid),
+                    None => empty_clusters.push(ci),
                 }
-                let new_centroid = array.mean_axis(Axis(0)).expect("Should be > 0");
-                centroids.row_mut(ci).assign(&new_centroid);
             }
 
-            if abs_diff_eq!(centroids, previous_centroids, epsilon = self.tolerance) {
-                inertia = run_inertia / dataset.nrows() as f64;
+            if !empty_clusters.is_empty() {
+                // Accelerated assignments report stale upper bounds for the points they
+                // skipped, so relocate from exact distances like `Lloyd` does
+                distances = map_indexes(dataset.nrows(), |ei| {
+                    euclidean_distance(dataset.row(ei), previous_centroids.row(labels[ei]))
+                });
+            }
+            for ci in empty_clusters {
+                self.relocate_empty_cluster(
+                    ci,
+                    dataset,
+                    &clustered_data_indexes,
+                    &mut distances,
+                    &mut centroids,
+                )?;
+            }
+
+            if abs_diff_eq!(
+                centroids,
+                previous_centroids,
+                epsilon = F::cast(self.tolerance)
+            ) {
+                converged = true;
                 break;
             }
+            assignment.centroids_moved(&previous_centroids, &centroids);
         }
-        Model { centroids, inertia }
-    }
-
-    fn plus_plus_init(&self, dataset: &Array2<f64>) -> Array2<f64> {
-        let mut centroid_indexes: Vec<usize> = Vec::new();
-        centroid_indexes.push(thread_rng().gen_range(0..dataset.nrows()));
 
-        while centroid_indexes.len() < self.n_clusters as usize {
-            let mut max_distance: (usize, f64) = (0, 0.0); // (index of point, distance)
-            for i in 0..dataset.nrows() {
-                if centroid_indexes.contains(&i) {
-                    continue;
-                }
+        Ok(Model::new(dataset, centroids, converged, n_iterations))
+    }
 
-                for ci in &centroid_indexes {
-                    let distance = euclidean_distance(dataset.row(i), dataset.row(*ci));
-                    if distance > max_distance.1 {
-                        max_distance = (i, distance);
-                    }
-                }
-            }
-            centroid_indexes.push(max_distance.0);
-        }
+    /// Applies the empty cluster policy to centroid `ci`. A point used as a new centroid has
+    /// its distance zeroed, so several empty clusters in one iteration get different points.
+    fn relocate_empty_cluster<F: Float>(
+        &self,
+        ci: usize,
+        dataset: ArrayView2<F>,
+        clustered_data_indexes: &[Vec<usize>],
+        distances: &mut [F],
+        centroids: &mut Array2<F>,
+    ) -> Result<()> {
+        let point = match self.empty_cluster {
+            EmptyCluster::KeepPrevious => return Ok(()),
+            EmptyCluster::Error => return Err(Error::EmptyCluster(ci)),
+            EmptyCluster::FarthestPoint => farthest_point(0..dataset.nrows(), distances),
+            EmptyCluster::LargestError => clustered_data_indexes
+                .iter()
+                .max_by(|a, b| {
+                    let error = |indexes: &&Vec<usize>| -> F {
+                        indexes.iter().map(|ei| distances[*ei].powi(2)).sum()
+                    };
+                    error(a).total_cmp(&error(b))
+                })
+                .and_then(|indexes| farthest_point(indexes.iter().copied(), distances)),
+        };
 
-        let mut array: Array2<f64> = Array2::zeros((0, dataset.ncols()));
-        for a in centroid_indexes.into_iter().map(|i| dataset.row(i)) {
-            array.push_row(a).expect("Row lengths match");
+        if let Some(ei) = point {
+            centroids.row_mut(ci).assign(&dataset.row(ei));
+            distances[ei] = F::zero();
         }
-        array
+        Ok(())
     }
 
     fn init_clustered_data_index_vec(&self) -> Vec<Vec<usize>> {
@@ -109,26 +257,203 @@ impl KMeans {
     }
 }
 
-pub struct Model {
-    centroids: Array2<f64>,
-    inertia: f64,
+/// Fitted centroids. `D` is the metric used to find the closest centroid of new points;
+/// fitting itself always minimises squared `Euclidean` distances.
+#[derive(Clone, PartialEq)]
+pub struct Model<F = f64, D = Euclidean> {
+    centroids: Array2<F>,
+    metric: D,
+    labels: Array1<usize>,
+    counts: Vec<usize>,
+    total_inertia: F,
+    inertia: F,
+    converged: bool,
+    n_iterations: u32,
+    run_inertias: Vec<F>,
 }
 
-impl Model {
-    pub fn centroids(&self) -> Array2<f64> {
+impl<F: Float> Model<F> {
+    fn new(
+        dataset: ArrayView2<F>,
+        centroids: Array2<F>,
+        converged: bool,
+        n_iterations: u32,
+    ) -> Self {
+        let closest = map_indexes(dataset.nrows(), |i| {
+            get_closest_centroid(dataset.row(i), &centroids, &Euclidean)
+        });
+        let mut labels = Vec::with_capacity(closest.len());
+        let mut counts = vec![0; centroids.nrows()];
+        let mut total_inertia = F::zero();
+        for (ci, distance) in closest {
+            labels.push(ci);
+            counts[ci] += 1;
+            total_inertia += distance.powi(2);
+        }
+        let inertia = total_inertia / F::cast(dataset.nrows() as f64);
+        Model {
+            centroids,
+            metric: Euclidean,
+            labels: Array1::from(labels),
+            counts,
+            total_inertia,
+            inertia,
+            converged,
+            n_iterations,
+            run_inertias: vec![inertia],
+        }
+    }
+}
+
+impl<F: Float, D: Distance<F>> Model<F, D> {
+    /// Uses `metric` for `predict`, `predict_many`, `transform` and `partial_fit`.
+    pub fn with_metric<M: Distance<F>>(self, metric: M) -> Model<F, M> {
+        Model {
+            centroids: self.centroids,
+            metric,
+            labels: self.labels,
+            counts: self.counts,
+            total_inertia: self.total_inertia,
+            inertia: self.inertia,
+            converged: self.converged,
+            n_iterations: self.n_iterations,
+            run_inertias: self.run_inertias,
+        }
+    }
+
+    pub fn centroids(&self) -> Array2<F> {
         self.centroids.clone()
     }
 
-    pub fn inertia(&self) -> f64 {
+    /// Closest centroid of every sample passed to `fit`.
+    pub fn labels(&self) -> &Array1<usize> {
+        &self.labels
+    }
+
+    /// Number of samples absorbed by each centroid, including those from `partial_fit`.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Mean squared distance from each sample to its closest centroid.
+    pub fn inertia(&self) -> F {
         self.inertia
     }
 
-    pub fn predict(&self, point: ArrayView1<f64>) -> usize {
-        get_closest_centroid(point, &self.centroids).0
+    /// Sum of squared distances from each sample to its closest centroid.
+    pub fn total_inertia(&self) -> F {
+        self.total_inertia
+    }
+
+    /// Whether centroids settled within `tolerance` before `max_n_iterations` was reached.
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+
+    pub fn n_iterations(&self) -> u32 {
+        self.n_iterations
+    }
+
+    /// Inertia of every run performed by `fit`, in the order they were run.
+    pub fn run_inertias(&self) -> &[F] {
+        &self.run_inertias
+    }
+
+    /// Fails if `point` has a different number of values than the centroids or a NaN value,
+    /// or if the metric doesn't fit the columns.
+    pub fn predict(&self, point: ArrayView1<F>) -> Result<usize> {
+        self.metric.check_columns(self.centroids.ncols())?;
+        if point.len() != self.centroids.ncols() {
+            return Err(Error::ShapeMismatch {
+                expected: (1, self.centroids.ncols()),
+                found: (1, point.len()),
+            });
+        }
+        check_nan(&point.insert_axis(Axis(0)))?;
+        Ok(get_closest_centroid(point, &self.centroids, &self.metric).0)
+    }
+
+    pub fn predict_many<S: Data<Elem = F>>(
+        &self,
+        points: &ArrayBase<S, Ix2>,
+    ) -> Result<Array1<usize>> {
+        Predict::predict(self, points.view())
+    }
+
+    /// Distance from every point (rows) to every centroid (columns).
+    pub fn transform<S: Data<Elem = F>>(&self, points: &ArrayBase<S, Ix2>) -> Result<Array2<F>> {
+        Transform::transform(self, points.view())
+    }
+
+    /// Online k-means update: every point of `batch` moves its closest centroid towards itself
+    /// by `1 / count`, so each centroid stays the running mean of all samples it absorbed.
+    /// Inertia and convergence info keep describing the data passed to `fit`.
+    pub fn partial_fit<S: Data<Elem = F>>(&mut self, batch: &ArrayBase<S, Ix2>) -> Result<()> {
+        self.check_points(batch.view())?;
+
+        for point in batch.rows() {
+            let ci = get_closest_centroid(point, &self.centroids, &self.metric).0;
+            self.counts[ci] += 1;
+            let rate = F::cast(1.0 / self.counts[ci] as f64);
+            let mut centroid = self.centroids.row_mut(ci);
+            centroid *= F::one() - rate;
+            centroid.scaled_add(rate, &point);
+        }
+        Ok(())
+    }
+
+    /// Fails unless `points` has as many columns as the centroids and no NaN values, or if
+    /// the metric doesn't fit the columns.
+    fn check_points(&self, points: ArrayView2<F>) -> Result<()> {
+        self.metric.check_columns(self.centroids.ncols())?;
+        if points.ncols() != self.centroids.ncols() {
+            return Err(Error::ShapeMismatch {
+                expected: (points.nrows(), self.centroids.ncols()),
+                found: points.dim(),
+            });
+        }
+        check_nan(&points)
+    }
+}
+
+impl<F: Float> Fit<F> for KMeans {
+    type Object = Model<F>;
+
+    fn fit(&self, data: ArrayView2<F>) -> Result<Model<F>> {
+        KMeans::fit(self, &data)
+    }
+}
+
+impl<F: Float, D: Distance<F>> Predict<F> for Model<F, D> {
+    type Output = Array1<usize>;
+
+    /// Closest centroid of every row.
+    fn predict(&self, data: ArrayView2<F>) -> Result<Array1<usize>> {
+        self.check_points(data)?;
+        let labels = map_indexes(data.nrows(), |i| {
+            get_closest_centroid(data.row(i), &self.centroids, &self.metric).0
+        });
+        Ok(Array1::from(labels))
     }
 }
 
-impl Debug for Model {
+impl<F: Float, D: Distance<F>> Transform<F> for Model<F, D> {
+    /// Distance from every row to every centroid (columns).
+    fn transform(&self, data: ArrayView2<F>) -> Result<Array2<F>> {
+        self.check_points(data)?;
+        let rows = map_indexes(data.nrows(), |i| {
+            self.centroids
+                .rows()
+                .into_iter()
+                .map(|centroid| self.metric.distance(data.row(i), centroid))
+                .collect::<Vec<F>>()
+        });
+        let shape = (data.nrows(), self.centroids.nrows());
+        Ok(Array2::from_shape_vec(shape, rows.concat()).expect("Shape matches"))
+    }
+}
+
+impl<F: Float, D> Debug for Model<F, D> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "[")?;
 
@@ -148,13 +473,126 @@ impl Debug for Model {
     }
 }
 
-fn get_closest_centroid(point: ArrayView1<f64>, centroids: &Array2<f64>) -> (usize, f64) {
-    let mut closest_centroid = (0, f64::max_value());
+/// Fails on an empty dataset, NaN values and a number of clusters outside `1..=nrows`.
+fn check_dataset<F: Float>(dataset: ArrayView2<F>, n_clusters: usize) -> Result<()> {
+    if dataset.nrows() == 0 {
+        return Err(Error::EmptyDataset);
+    }
+    if n_clusters == 0 || n_clusters > dataset.nrows() {
+        return Err(Error::InvalidClusters {
+            n_clusters,
+            n_samples: dataset.nrows(),
+        });
+    }
+    check_nan(&dataset)
+}
+
+fn get_closest_centroid<F: Float, D: Distance<F>>(
+    point: ArrayView1<F>,
+    centroids: &Array2<F>,
+    metric: &D,
+) -> (usize, F) {
+    let mut closest_centroid = (0, F::max_value());
     for ci in 0..centroids.nrows() {
-        let distance = euclidean_distance(point, centroids.row(ci));
+        let distance = metric.distance(point, centroids.row(ci));
         if distance < closest_centroid.1 {
             closest_centroid = (ci, distance);
         }
     }
     closest_centroid
 }
+
+fn farthest_point<F: Float>(
+    indexes: impl Iterator<Item = usize>,
+    distances: &[F],
+) -> Option<usize> {
+    indexes.max_by(|a, b| distances[*a].total_cmp(&distances[*b]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    /// Three loose blobs, so the clustering takes several iterations to settle.
+    fn blobs() -> Array2<f64> {
+        let mut rng = Xoshiro256Plus::seed_from_u64(7);
+        let centres = [[0.0, 0.0], [4.0, 1.0], [1.0, 5.0]];
+        Array2::from_shape_fn((300, 2), |(i, j)| {
+            centres[i % 3][j] + rng.gen_range(-2.0..2.0)
+        })
+    }
+
+    #[test]
+    fn same_seed_gives_same_model() {
+        let data = blobs();
+        let mut kmeans = KMeans::new(3, 1e-6, 100);
+        kmeans.n_init(3).seed(11);
+        assert_eq!(kmeans.fit(&data).unwrap(), kmeans.fit(&data).unwrap());
+    }
+
+    #[test]
+    fn accelerated_algorithms_match_lloyd() {
+        let data = blobs();
+        let mut kmeans = KMeans::new(5, 0.0, 100);
+        kmeans.seed(3);
+        let lloyd = kmeans.fit(&data).unwrap();
+        for algorithm in [Algorithm::Elkan, Algorithm::Hamerly] {
+            let model = kmeans.algorithm(algorithm).fit(&data).unwrap();
+            assert_eq!(model.labels(), lloyd.labels(), "{:?}", algorithm);
+            assert_eq!(
+                model.n_iterations(),
+                lloyd.n_iterations(),
+                "{:?}",
+                algorithm
+            );
+            assert_abs_diff_eq!(model.centroids(), lloyd.centroids(), epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn accelerated_algorithms_relocate_like_lloyd() {
+        // Cluster 0 starts away from every point, and a cluster empties again in a later
+        // iteration, when Elkan and Hamerly only hold bounds for most distances
+        let data = Array2::from_shape_vec(
+            (12, 1),
+            vec![
+                4.665883876584007,
+                11.86594284085917,
+                12.69472063428124,
+                10.743403233196727,
+                12.36889606833136,
+                18.429835412101475,
+                3.098598316182284,
+                3.8770690257889795,
+                0.4389233554702576,
+                15.63467661519756,
+                2.2148744059711056,
+                11.41857444381785,
+            ],
+        )
+        .unwrap();
+        let init = Array2::from_shape_vec(
+            (4, 1),
+            vec![
+                19.77183116044156,
+                2.5776984866042874,
+                5.020933875828129,
+                18.146746679487713,
+            ],
+        )
+        .unwrap();
+        for policy in [EmptyCluster::FarthestPoint, EmptyCluster::LargestError] {
+            let mut kmeans = KMeans::new(4, 0.0, 100);
+            kmeans
+                .init(Init::Precomputed(init.clone()))
+                .empty_cluster(policy);
+            let lloyd = kmeans.fit(&data).unwrap();
+            for algorithm in [Algorithm::Elkan, Algorithm::Hamerly] {
+                let model = kmeans.algorithm(algorithm).fit(&data).unwrap();
+                assert_eq!(model.labels(), lloyd.labels(), "{:?}", algorithm);
+                assert_abs_diff_eq!(model.centroids(), lloyd.centroids(), epsilon = 1e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use super::get_closest_centroid;
+use crate::distance::Euclidean;
+use crate::euclidean_distance;
+use crate::float::Float;
+use crate::parallel::{for_each_mut, map_indexes};
+use ndarray::{Array2, ArrayView1, ArrayView2};
+
+/// Assignment step used by `KMeans::fit`. The accelerated variants keep distance bounds between
+/// iterations (triangle inequality) to skip most point-to-centroid distances, yet assign every
+/// point to the same centroid as `Lloyd` (up to ties between equally distant centroids).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Computes the distance from every point to every centroid.
+    #[default]
+    Lloyd,
+    /// Keeps an upper bound and one lower bound per centroid for every point.
+    /// Fastest with many clusters, needs `n_points * n_clusters` extra memory.
+    Elkan,
+    /// Keeps an upper bound and a single lower bound for every point.
+    /// Best suited for a small number of clusters.
+    Hamerly,
+}
+
+impl Algorithm {
+    pub(super) fn assignment<F: Float>(
+        &self,
+        n_points: usize,
+        n_clusters: usize,
+    ) -> Box<dyn Assignment<F>> {
+        match self {
+            Algorithm::Lloyd => Box::new(Lloyd),
+            Algorithm::Elkan => Box::new(Elkan {
+                bounds: vec![
+                    ElkanBounds {
+                        label: 0,
+                        upper: F::infinity(),
+                        lower: vec![F::zero(); n_clusters],
+                    };
+                    n_points
+                ],
+                initialized: false,
+            }),
+            Algorithm::Hamerly => Box::new(Hamerly {
+                bounds: vec![
+                    HamerlyBounds {
+                        label: 0,
+                        upper: F::infinity(),
+                        lower: F::zero(),
+                    };
+                    n_points
+                ],
+                initialized: false,
+            }),
+        }
+    }
+}
+
+pub(super) trait Assignment<F> {
+    /// Returns the closest centroid of every point along with the distance to it. Accelerated
+    /// variants may report an upper bound of that distance instead.
+    fn assign(&mut self, dataset: ArrayView2<F>, centroids: &Array2<F>) -> (Vec<usize>, Vec<F>);
+
+    /// Updates the bounds after the centroids moved from `previous` to `centroids`.
+    fn centroids_moved(&mut self, previous: &Array2<F>, centroids: &Array2<F>);
+}
+
+struct Lloyd;
+
+impl<F: Float> Assignment<F> for Lloyd {
+    fn assign(&mut self, dataset: ArrayView2<F>, centroids: &Array2<F>) -> (Vec<usize>, Vec<F>) {
+        map_indexes(dataset.nrows(), |i| {
+            get_closest_centroid(dataset.row(i), centroids, &Euclidean)
+        })
+        .into_iter()
+        .unzip()
+    }
+
+    fn centroids_moved(&mut self, _: &Array2<F>, _: &Array2<F>) {}
+}
+
+/// Bounds of one point; `lower[ci]` bounds the distance to centroid `ci`.
+#[derive(Clone)]
+struct ElkanBounds<F> {
+    label: usize,
+    upper: F,
+    lower: Vec<F>,
+}
+
+struct Elkan<F> {
+    bounds: Vec<ElkanBounds<F>>,
+    initialized: bool,
+}
+
+impl<F: Float> Elkan<F> {
+    /// Computes every distance of a point, refreshing its label and all bounds.
+    fn assign_exact(bounds: &mut ElkanBounds<F>, point: ArrayView1<F>, centroids: &Array2<F>) {
+        bounds.upper = F::infinity();
+        for (ci, centroid) in centroids.rows().into_iter().enumerate() {
+            let distance = euclidean_distance(point, centroid);
+            bounds.lower[ci] = distance;
+            if distance < bounds.upper {
+                bounds.upper = distance;
+                bounds.label = ci;
+            }
+        }
+    }
+
+    /// Skips every centroid that the bounds rule out for a point.
+    fn assign_bounded(
+        bounds: &mut ElkanBounds<F>,
+        point: ArrayView1<F>,
+        centroids: &Array2<F>,
+        centroid_distances: &Array2<F>,
+        half_separation: &[F],
+    ) {
+        if bounds.upper <= half_separation[bounds.label] {
+            return;
+        }
+
+        let half = F::cast(0.5);
+        let mut upper_is_tight = false;
+        for ci in 0..centroids.nrows() {
+            let label = bounds.label;
+            if ci == label
+                || bounds.upper <= bounds.lower[ci]
+                || bounds.upper <= half * centroid_distances[[label, ci]]
+            {
+                continue;
+            }
+
+            if !upper_is_tight {
+                bounds.upper = euclidean_distance(point, centroids.row(label));
+                bounds.lower[label] = bounds.upper;
+                upper_is_tight = true;
+                if bounds.upper <= bounds.lower[ci]
+                    || bounds.upper <= half * centroid_distances[[label, ci]]
+                {
+                    continue;
+                }
+            }
+
+            let distance = euclidean_distance(point, centroids.row(ci));
+            bounds.lower[ci] = distance;
+            if distance < bounds.upper {
+                bounds.upper = distance;
+                bounds.label = ci;
+            }
+        }
+    }
+}
+
+impl<F: Float> Assignment<F> for Elkan<F> {
+    fn assign(&mut self, dataset: ArrayView2<F>, centroids: &Array2<F>) -> (Vec<usize>, Vec<F>) {
+        if !self.initialized {
+            for_each_mut(&mut self.bounds, |i, bounds| {
+                Self::assign_exact(bounds, dataset.row(i), centroids)
+            });
+            self.initialized = true;
+        } else {
+            let centroid_distances = centroid_distances(centroids);
+            let half_separation = half_separation(&centroid_distances);
+            for_each_mut(&mut self.bounds, |i, bounds| {
+                Self::assign_bounded(
+                    bounds,
+                    dataset.row(i),
+                    centroids,
+                    &centroid_distances,
+                    &half_separation,
+                )
+            });
+        }
+        self.bounds.iter().map(|b| (b.label, b.upper)).unzip()
+    }
+
+    fn centroids_moved(&mut self, previous: &Array2<F>, centroids: &Array2<F>) {
+        let shifts = shifts(previous, centroids);
+        for_each_mut(&mut self.bounds, |_, bounds| {
+            for (bound, shift) in bounds.lower.iter_mut().zip(&shifts) {
+                *bound = (*bound - *shift).max(F::zero());
+            }
+            bounds.upper += shifts[bounds.label];
+        });
+    }
+}
+
+/// Bounds of one point; `lower` bounds the distance to every centroid but its own.
+#[derive(Clone, Copy)]
+struct HamerlyBounds<F> {
+    label: usize,
+    upper: F,
+    lower: F,
+}
+
+struct Hamerly<F> {
+    bounds: Vec<HamerlyBounds<F>>,
+    initialized: bool,
+}
+
+impl<F: Float> Hamerly<F> {
+    /// Computes every distance of a point, refreshing its label and both bounds.
+    fn assign_exact(bounds: &mut HamerlyBounds<F>, point: ArrayView1<F>, centroids: &Array2<F>) {
+        let mut closest = (0, F::infinity());
+        let mut second_closest = F::infinity();
+        for (ci, centroid) in centroids.rows().into_iter().enumerate() {
+            let distance = euclidean_distance(point, centroid);
+            if distance < closest.1 {
+                second_closest = closest.1;
+                closest = (ci, distance);
+            } else if distance < second_closest {
+                second_closest = distance;
+            }
+        }
+        bounds.label = closest.0;
+        bounds.upper = closest.1;
+        bounds.lower = second_closest;
+    }
+}
+
+impl<F: Float> Assignment<F> for Hamerly<F> {
+    fn assign(&mut self, dataset: ArrayView2<F>, centroids: &Array2<F>) -> (Vec<usize>, Vec<F>) {
+        if !self.initialized {
+            for_each_mut(&mut self.bounds, |i, bounds| {
+                Self::assign_exact(bounds, dataset.row(i), centroids)
+            });
+            self.initialized = true;
+        } else {
+            let half_separation = half_separation(&centroid_distances(centroids));
+            for_each_mut(&mut self.bounds, |i, bounds| {
+                let bound = half_separation[bounds.label].max(bounds.lower);
+                if bounds.upper <= bound {
+                    return;
+                }
+
+                bounds.upper = euclidean_distance(dataset.row(i), centroids.row(bounds.label));
+                if bounds.upper <= bound {
+                    return;
+                }
+                Self::assign_exact(bounds, dataset.row(i), centroids);
+            });
+        }
+        self.bounds.iter().map(|b| (b.label, b.upper)).unzip()
+    }
+
+    fn centroids_moved(&mut self, previous: &Array2<F>, centroids: &Array2<F>) {
+        let shifts = shifts(previous, centroids);
+        let mut largest = (0, F::zero());
+        let mut second_largest = F::zero();
+        for (ci, shift) in shifts.iter().enumerate() {
+            if *shift > largest.1 {
+                second_largest = largest.1;
+                largest = (ci, *shift);
+            } else if *shift > second_largest {
+                second_largest = *shift;
+            }
+        }
+
+        for_each_mut(&mut self.bounds, |_, bounds| {
+            bounds.upper += shifts[bounds.label];
+            let other_shift = if bounds.label == largest.0 {
+                second_largest
+            } else {
+                largest.1
+            };
+            bounds.lower = (bounds.lower - other_shift).max(F::zero());
+        });
+    }
+}
+
+fn shifts<F: Float>(previous: &Array2<F>, centroids: &Array2<F>) -> Vec<F> {
+    previous
+        .rows()
+        .into_iter()
+        .zip(centroids.rows())
+        .map(|(a, b)| euclidean_distance(a, b))
+        .collect()
+}
+
+fn centroid_distances<F: Float>(centroids: &Array2<F>) -> Array2<F> {
+    let n = centroids.nrows();
+    let mut distances = Array2::zeros((n, n));
+    for a in 0..n {
+        for b in a + 1..n {
+            let distance = euclidean_distance(centroids.row(a), centroids.row(b));
+            distances[[a, b]] = distance;
+            distances[[b, a]] = distance;
+        }
+    }
+    distances
+}
+
+/// Half the distance from every centroid to its closest other centroid. A point closer than
+/// that to its centroid cannot be closer to any other one.
+fn half_separation<F: Float>(centroid_distances: &Array2<F>) -> Vec<F> {
+    centroid_distances
+        .rows()
+        .into_iter()
+        .enumerate()
+        .map(|(ci, row)| {
+            let closest = row
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != ci)
+                .map(|(_, distance)| *distance)
+                .fold(F::infinity(), F::min);
+            F::cast(0.5) * closest
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+use crate::error::{Error, Result};
+use crate::euclidean_distance;
+use crate::float::Float;
+use crate::parallel::map_indexes;
+use ndarray::{Array2, ArrayView1, ArrayView2, Axis};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::index::sample;
+use rand::Rng;
+
+/// How `KMeans` picks the starting centroids of every run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Init {
+    /// k-means++: every next centroid is sampled with probability proportional to the
+    /// squared distance to the closest centroid chosen so far.
+    #[default]
+    KMeansPlusPlus,
+    /// Greedy k-means++: samples several candidates per step and keeps the one that reduces
+    /// the total squared distance the most. `None` uses `2 + ln(k)` candidates.
+    GreedyKMeansPlusPlus(Option<usize>),
+    /// Assigns every point to a random cluster and starts from the cluster means.
+    RandomPartition,
+    /// Picks distinct random points as centroids.
+    Forgy,
+    /// Starts from a random point, then repeatedly takes the point farthest from all chosen
+    /// centroids.
+    FarthestFirst,
+    /// Uses the given centroids, one per row, cast to the type of the dataset.
+    Precomputed(Array2<f64>),
+}
+
+impl Init {
+    pub(super) fn centroids<F: Float, R: Rng + ?Sized>(
+        &self,
+        dataset: ArrayView2<F>,
+        n_clusters: usize,
+        rng: &mut R,
+    ) -> Result<Array2<F>> {
+        let indexes = match self {
+            Init::KMeansPlusPlus => plus_plus(dataset, n_clusters, 1, rng),
+            Init::GreedyKMeansPlusPlus(n_candidates) => {
+                let default = 2 + (n_clusters as f64).ln() as usize;
+                plus_plus(
+                    dataset,
+                    n_clusters,
+                    n_candidates.unwrap_or(default).max(1),
+                    rng,
+                )
+            }
+            Init::RandomPartition => return Ok(random_partition(dataset, n_clusters, rng)),
+            Init::Forgy => forgy(dataset.nrows(), n_clusters, rng),
+            Init::FarthestFirst => farthest_first(dataset, n_clusters, rng),
+            Init::Precomputed(centroids) => {
+                let expected = (n_clusters, dataset.ncols());
+                if centroids.dim() != expected {
+                    return Err(Error::ShapeMismatch {
+                        expected,
+                        found: centroids.dim(),
+                    });
+                }
+                return Ok(centroids.mapv(F::cast));
+            }
+        };
+        Ok(dataset.select(Axis(0), &indexes))
+    }
+}
+
+/// Computed in `F`, returned as `f64` so it can weigh the k-means++ sampling.
+fn squared_distance<F: Float>(point1: ArrayView1<F>, point2: ArrayView1<F>) -> f64 {
+    let distance = euclidean_distance(point1, point2).powi(2);
+    distance.to_f64().expect("Float converts to f64")
+}
+
+/// Keeps `closest` holding each point's squared distance to its nearest chosen centroid.
+fn update_closest<F: Float>(dataset: ArrayView2<F>, centroid: usize, closest: &mut [f64]) {
+    let distances = map_indexes(dataset.nrows(), |i| {
+        squared_distance(dataset.row(i), dataset.row(centroid))
+    });
+    for (each, distance) in closest.iter_mut().zip(distances) {
+        *each = each.min(distance);
+    }
+}
+
+fn plus_plus<F: Float, R: Rng + ?Sized>(
+    dataset: ArrayView2<F>,
+    n_clusters: usize,
+    n_candidates: usize,
+    rng: &mut R,
+) -> Vec<usize> {
+    let mut centroid_indexes = vec![rng.gen_range(0..dataset.nrows())];
+    let mut closest = vec![f64::INFINITY; dataset.nrows()];
+    update_closest(dataset, centroid_indexes[0], &mut closest);
+
+    while centroid_indexes.len() < n_clusters {
+        // All weights are zero once every distinct point is a centroid
+        let distribution = match WeightedIndex::new(&closest) {
+            Ok(distribution) => distribution,
+            Err(_) => {
+                let index = rng.gen_range(0..dataset.nrows());
+                centroid_indexes.push(index);
+                continue;
+            }
+        };
+
+        let mut best: Option<(usize, Vec<f64>, f64)> = None; // (index, closest, potential)
+        for _ in 0..n_candidates {
+            let candidate = distribution.sample(rng);
+            let mut candidate_closest = closest.clone();
+            update_closest(dataset, candidate, &mut candidate_closest);
+            let potential: f64 = candidate_closest.iter().sum();
+            if best.as_ref().is_none_or(|b| potential < b.2) {
+                best = Some((candidate, candidate_closest, potential));
+            }
+        }
+
+        let (index, best_closest, _) = best.expect("At least one candidate");
+        centroid_indexes.push(index);
+        closest = best_closest;
+    }
+    centroid_indexes
+}
+
+fn random_partition<F: Float, R: Rng + ?Sized>(
+    dataset: ArrayView2<F>,
+    n_clusters: usize,
+    rng: &mut R,
+) -> Array2<F> {
+    let mut sums: Array2<F> = Array2::zeros((n_clusters, dataset.ncols()));
+    let mut counts = vec![0usize; n_clusters];
+    for point in dataset.rows() {
+        let ci = rng.gen_range(0..n_clusters);
+        let mut sum = sums.row_mut(ci);
+        sum += &point;
+        counts[ci] += 1;
+    }
+
+    for (ci, count) in counts.into_iter().enumerate() {
+        let mut centroid = sums.row_mut(ci);
+        if count == 0 {
+            centroid.assign(&dataset.row(rng.gen_range(0..dataset.nrows())));
+        } else {
+            centroid /= F::cast(count as f64);
+        }
+    }
+    sums
+}
+
+fn forgy<R: Rng + ?Sized>(n_points: usize, n_clusters: usize, rng: &mut R) -> Vec<usize> {
+    let mut centroid_indexes = sample(rng, n_points, n_clusters.min(n_points)).into_vec();
+    while centroid_indexes.len() < n_clusters {
+        centroid_indexes.push(rng.gen_range(0..n_points));
+    }
+    centroid_indexes
+}
+
+fn farthest_first<F: Float, R: Rng + ?Sized>(
+    dataset: ArrayView2<F>,
+    n_clusters: usize,
+    rng: &mut R,
+) -> Vec<usize> {
+    let mut centroid_indexes = vec![rng.gen_range(0..dataset.nrows())];
+    let mut closest = vec![f64::INFINITY; dataset.nrows()];
+    update_closest(dataset, centroid_indexes[0], &mut closest);
+
+    while centroid_indexes.len() < n_clusters {
+        let (index, _) = closest
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .expect("Dataset should not be empty");
+        centroid_indexes.push(index);
+        update_closest(dataset, index, &mut closest);
+    }
+    centroid_indexes
+}
@@ -0,0 +1,80 @@
+use super::{Init, KMeans, Model};
+use crate::distance::Distance;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::traits::Predict;
+use ::linfa::traits::{Fit, PredictInplace};
+use ::linfa::{DatasetBase, ParamGuard};
+use ndarray::{Array1, ArrayBase, Data, Ix2};
+
+/// Unchecked `KMeans` settings for linfa: `fit` validates them first, so they can be passed
+/// to linfa's cross-validation and metrics tooling. `Model` implements linfa's
+/// `PredictInplace` for batches of records.
+pub struct KMeansParams(KMeans);
+
+impl From<KMeans> for KMeansParams {
+    fn from(kmeans: KMeans) -> Self {
+        KMeansParams(kmeans)
+    }
+}
+
+impl ParamGuard for KMeansParams {
+    type Checked = KMeans;
+    type Error = Error;
+
+    /// Fails on zero clusters, a negative or NaN tolerance and precomputed centroids of the
+    /// wrong count.
+    fn check_ref(&self) -> Result<&KMeans> {
+        let kmeans = &self.0;
+        if kmeans.n_clusters == 0 {
+            return Err(Error::InvalidParameter(
+                "n_clusters must be positive".to_owned(),
+            ));
+        }
+        if kmeans.tolerance.is_nan() || kmeans.tolerance < 0.0 {
+            return Err(Error::InvalidParameter(format!(
+                "tolerance must be non-negative, found {}",
+                kmeans.tolerance
+            )));
+        }
+        if let Init::Precomputed(centroids) = &kmeans.init {
+            if centroids.nrows() != kmeans.n_clusters as usize {
+                return Err(Error::InvalidParameter(format!(
+                    "{} precomputed centroids for {} clusters",
+                    centroids.nrows(),
+                    kmeans.n_clusters
+                )));
+            }
+        }
+        Ok(kmeans)
+    }
+
+    fn check(self) -> Result<KMeans> {
+        self.check_ref()?;
+        Ok(self.0)
+    }
+}
+
+impl<F: Float, S: Data<Elem = F>, T> Fit<ArrayBase<S, Ix2>, T, Error> for KMeans {
+    type Object = Model<F>;
+
+    fn fit(&self, dataset: &DatasetBase<ArrayBase<S, Ix2>, T>) -> Result<Model<F>> {
+        KMeans::fit(self, &dataset.records)
+    }
+}
+
+impl<F: Float, D: Distance<F>, S: Data<Elem = F>> PredictInplace<ArrayBase<S, Ix2>, Array1<usize>>
+    for Model<F, D>
+{
+    /// Panics if `x` has a different number of columns than the centroids or NaN values, or
+    /// `y` a different length than `x` has rows.
+    fn predict_inplace(&self, x: &ArrayBase<S, Ix2>, y: &mut Array1<usize>) {
+        assert_eq!(x.nrows(), y.len(), "Targets match the records");
+        let labels = Predict::predict(self, x.view()).expect("Records are valid queries");
+        y.assign(&labels);
+    }
+
+    fn default_target(&self, x: &ArrayBase<S, Ix2>) -> Array1<usize> {
+        Array1::zeros(x.nrows())
+    }
+}
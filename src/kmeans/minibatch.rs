@@ -0,0 +1,208 @@
+use super::{check_dataset, get_closest_centroid, Init, Model};
+use crate::distance::Euclidean;
+use crate::error::Result;
+use crate::float::Float;
+use crate::traits::Fit;
+use approx::abs_diff_eq;
+use ndarray::{Array2, ArrayBase, ArrayView2, Axis, Data, Ix2};
+use rand::seq::index::sample;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256Plus;
+
+/// Step size used when a centroid is pulled towards a sample of the batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LearningRate {
+    /// `1 / n`, where `n` is the number of samples the centroid has absorbed so far. Every
+    /// centroid ends up as the running mean of its samples.
+    #[default]
+    PerCenter,
+    /// The same rate for every step.
+    Constant(f64),
+    /// `initial / (iteration + 1) ^ power`.
+    InverseScaling { initial: f64, power: f64 },
+}
+
+impl LearningRate {
+    fn rate(&self, count: usize, iteration: u32) -> f64 {
+        match *self {
+            LearningRate::PerCenter => 1.0 / count as f64,
+            LearningRate::Constant(rate) => rate,
+            LearningRate::InverseScaling { initial, power } => {
+                initial / (iteration as f64 + 1.0).powf(power)
+            }
+        }
+    }
+}
+
+/// Mini-batch k-means: every iteration updates the centroids from a small random batch instead
+/// of the whole dataset, trading some accuracy for much cheaper iterations.
+pub struct MiniBatchKMeans {
+    n_clusters: u32,
+    batch_size: usize,
+    tolerance: f64,
+    max_n_iterations: u32,
+    learning_rate: LearningRate,
+    reassignment_ratio: f64,
+    init: Init,
+    seed: Option<u64>,
+}
+
+impl Default for MiniBatchKMeans {
+    fn default() -> Self {
+        Self::new(3, 1024, 1e-3, 100)
+    }
+}
+
+impl MiniBatchKMeans {
+    /// Iterations between checks for centroids that receive too few samples.
+    const REASSIGNMENT_INTERVAL: u32 = 10;
+
+    pub fn new(n_clusters: u32, batch_size: usize, tolerance: f64, max_n_iterations: u32) -> Self {
+        MiniBatchKMeans {
+            n_clusters,
+            batch_size,
+            tolerance,
+            max_n_iterations,
+            learning_rate: LearningRate::default(),
+            reassignment_ratio: 0.01,
+            init: Init::default(),
+            seed: None,
+        }
+    }
+
+    pub fn n_clusters(&mut self, value: u32) -> &mut Self {
+        self.n_clusters = value;
+        self
+    }
+
+    pub fn batch_size(&mut self, value: usize) -> &mut Self {
+        self.batch_size = value.max(1);
+        self
+    }
+
+    pub fn tolerance(&mut self, value: f64) -> &mut Self {
+        self.tolerance = value;
+        self
+    }
+
+    pub fn max_n_iterations(&mut self, value: u32) -> &mut Self {
+        self.max_n_iterations = value;
+        self
+    }
+
+    pub fn learning_rate(&mut self, value: LearningRate) -> &mut Self {
+        self.learning_rate = value;
+        self
+    }
+
+    /// Centroids that absorbed fewer than `value * max_count` samples are periodically moved to
+    /// random points of the current batch. `0.0` disables reassignment.
+    pub fn reassignment_ratio(&mut self, value: f64) -> &mut Self {
+        self.reassignment_ratio = value;
+        self
+    }
+
+    /// Initialisation is run on a random subset of `3 * batch_size` points.
+    pub fn init(&mut self, value: Init) -> &mut Self {
+        self.init = value;
+        self
+    }
+
+    pub fn seed(&mut self, value: u64) -> &mut Self {
+        self.seed = Some(value);
+        self
+    }
+
+    pub fn fit<F: Float, S: Data<Elem = F>>(
+        &self,
+        dataset: &ArrayBase<S, Ix2>,
+    ) -> Result<Model<F>> {
+        match self.seed {
+            Some(seed) => self.fit_with_rng(dataset, &mut Xoshiro256Plus::seed_from_u64(seed)),
+            None => self.fit_with_rng(dataset, &mut thread_rng()),
+        }
+    }
+
+    /// Same as `fit`, but all randomness is drawn from `rng` (the configured seed is ignored).
+    pub fn fit_with_rng<F: Float, S: Data<Elem = F>, R: Rng + ?Sized>(
+        &self,
+        dataset: &ArrayBase<S, Ix2>,
+        rng: &mut R,
+    ) -> Result<Model<F>> {
+        let dataset = dataset.view();
+        let n_clusters = self.n_clusters as usize;
+        check_dataset(dataset, n_clusters)?;
+        let init_size = (3 * self.batch_size).max(n_clusters).min(dataset.nrows());
+        let init_indexes = sample(rng, dataset.nrows(), init_size).into_vec();
+        let init_sample = dataset.select(Axis(0), &init_indexes);
+        let mut centroids = self.init.centroids(init_sample.view(), n_clusters, rng)?;
+
+        let mut counts = vec![0usize; n_clusters];
+        let mut converged = false;
+        let mut n_iterations = 0;
+        while n_iterations < self.max_n_iterations {
+            let previous_centroids = centroids.clone();
+            let batch_size = self.batch_size.min(dataset.nrows());
+            let batch = sample(rng, dataset.nrows(), batch_size).into_vec();
+
+            let labels: Vec<usize> = batch
+                .iter()
+                .map(|ei| get_closest_centroid(dataset.row(*ei), &centroids, &Euclidean).0)
+                .collect();
+            for (ei, ci) in batch.iter().zip(labels) {
+                counts[ci] += 1;
+                let rate = F::cast(self.learning_rate.rate(counts[ci], n_iterations).min(1.0));
+                let mut centroid = centroids.row_mut(ci);
+                centroid *= F::one() - rate;
+                centroid.scaled_add(rate, &dataset.row(*ei));
+            }
+
+            n_iterations += 1;
+            if n_iterations % Self::REASSIGNMENT_INTERVAL == 0 {
+                self.reassign_low_count_centers(dataset, &batch, &mut centroids, &mut counts, rng);
+            }
+
+            if abs_diff_eq!(
+                centroids,
+                previous_centroids,
+                epsilon = F::cast(self.tolerance)
+            ) {
+                converged = true;
+                break;
+            }
+        }
+
+        Ok(Model::new(dataset, centroids, converged, n_iterations))
+    }
+
+    fn reassign_low_count_centers<F: Float, R: Rng + ?Sized>(
+        &self,
+        dataset: ArrayView2<F>,
+        batch: &[usize],
+        centroids: &mut Array2<F>,
+        counts: &mut [usize],
+        rng: &mut R,
+    ) {
+        let max_count = counts.iter().copied().max().unwrap_or(0);
+        let threshold = self.reassignment_ratio * max_count as f64;
+        let (low, kept): (Vec<usize>, Vec<usize>) =
+            (0..counts.len()).partition(|ci| (counts[*ci] as f64) < threshold);
+        // Reassigned centroids start with the smallest kept count, so a single sample
+        // does not yank them around right away
+        let min_kept_count = kept.iter().map(|ci| counts[*ci]).min().unwrap_or(0);
+
+        for ci in low {
+            let ei = batch[rng.gen_range(0..batch.len())];
+            centroids.row_mut(ci).assign(&dataset.row(ei));
+            counts[ci] = min_kept_count;
+        }
+    }
+}
+
+impl<F: Float> Fit<F> for MiniBatchKMeans {
+    type Object = Model<F>;
+
+    fn fit(&self, data: ArrayView2<F>) -> Result<Model<F>> {
+        MiniBatchKMeans::fit(self, &data)
+    }
+}
@@ -1,54 +1,179 @@
-use crate::euclidean_distance;
-use ndarray::{Array2, ArrayView1, Axis};
-use std::collections::HashMap;
+use crate::distance::{Distance, Euclidean, Mahalanobis};
+use crate::error::Result;
+use crate::float::Float;
+use crate::traits::Predict;
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use search::{HasSearch, Search};
+use std::hash::Hash;
+use std::sync::Arc;
 
-pub struct KNearest {
+pub use hnsw::HnswParams;
+pub use index::IndexKind;
+pub use label_encoder::LabelEncoder;
+#[cfg(feature = "linfa")]
+pub use linfa::{
+    KNearestParams, KNearestRegressorParams, KNearestRegressorValidParams, KNearestValidParams,
+};
+pub use neighbours::Neighbours;
+pub use regressor::{Aggregate, KNearestRegressor};
+pub use voting::{TieBreak, Weights};
+
+mod ball_tree;
+mod hnsw;
+mod index;
+mod kd_tree;
+mod label_encoder;
+#[cfg(feature = "linfa")]
+mod linfa;
+mod neighbours;
+mod regressor;
+mod search;
+mod voting;
+mod vp_tree;
+
+pub struct KNearest<L = usize, F = f64, D = Euclidean> {
     k: usize,
-    data: Array2<f64>,
-    class_markers: Vec<usize>,
+    labels: LabelEncoder<L>,
+    /// Encoded class of every training row.
+    encoded: Vec<usize>,
+    weights: Weights,
+    tie_break: TieBreak,
+    search: Search<F, D>,
 }
 
-impl KNearest {
-    pub fn new(k: usize, data: Array2<f64>, class_markers: Vec<usize>) -> Self {
-        KNearest {
+impl<L: Eq + Hash + Clone, F: Float> KNearest<L, F> {
+    /// `classes` holds the label of every row of `data`. Fails if `data` is empty or has
+    /// NaN values, the lengths differ or `k` isn't between one and the number of rows.
+    /// Passing `data` as an `Arc` shares the rows with other models instead of moving them.
+    pub fn new(k: usize, data: impl Into<Arc<Array2<F>>>, classes: Vec<L>) -> Result<Self> {
+        let search = Search::new(data.into(), classes.len(), k)?;
+        let labels = LabelEncoder::fit(&classes);
+        let encoded = labels.transform(&classes).expect("Labels are fitted");
+        Ok(KNearest {
             k,
-            data,
-            class_markers,
+            labels,
+            encoded,
+            weights: Weights::default(),
+            tie_break: TieBreak::default(),
+            search,
+        })
+    }
+}
+
+impl<L: Eq + Hash + Clone, F: Float, D: Distance<F>> KNearest<L, F, D> {
+    /// Replaces the metric; any index built so far is dropped.
+    pub fn with_metric<M: Distance<F>>(self, metric: M) -> KNearest<L, F, M> {
+        KNearest {
+            k: self.k,
+            labels: self.labels,
+            encoded: self.encoded,
+            weights: self.weights,
+            tie_break: self.tie_break,
+            search: self.search.with_metric(metric),
         }
     }
 
+    pub fn weights(mut self, value: Weights) -> Self {
+        self.weights = value;
+        self
+    }
+
+    pub fn tie_break(mut self, value: TieBreak) -> Self {
+        self.tie_break = value;
+        self
+    }
+
+    /// Switches to a Mahalanobis metric with the covariance estimated from the training data.
+    /// See `Mahalanobis::fit` for `shrinkage`.
+    pub fn mahalanobis(self, shrinkage: f64) -> Result<KNearest<L, F, Mahalanobis<F>>> {
+        let metric = Mahalanobis::fit(self.search.data(), shrinkage)?;
+        Ok(self.with_metric(metric))
+    }
+
+    /// Estimates a separate covariance for every class: the distance to a training row is
+    /// measured with the covariance of that row's class. Classes with a single row use the
+    /// covariance of all training rows instead.
+    pub fn class_mahalanobis(mut self, shrinkage: f64) -> Result<Self> {
+        let groups = self.encoded.clone();
+        self.search = self
+            .search
+            .group_mahalanobis(groups, self.labels.len(), shrinkage)?;
+        Ok(self)
+    }
+
+    /// Number of distinct labels among the training rows.
     pub fn nclasses(&self) -> usize {
-        *self.class_markers.iter().max().unwrap()
+        self.labels.len()
     }
 
-    pub fn predict(&self, point: ArrayView1<f64>) -> usize {
-        let mut distances = Vec::new();
-        for (i, each) in self.data.axis_iter(Axis(0)).enumerate() {
-            let class = self.class_markers[i];
-            distances.push((class, euclidean_distance(point, each)))
-        }
+    /// Distinct labels of the training rows in order of first appearance; also the order of
+    /// `predict_proba` entries.
+    pub fn classes(&self) -> &[L] {
+        self.labels.classes()
+    }
 
-        distances.sort_by(|a, b| a.1.total_cmp(&b.1));
-        let nearest: Vec<usize> = distances.iter().map(|e| e.0).take(self.k).collect();
-        Self::find_dominant_class(&nearest)
+    /// Fails if `point` has a different number of values than the training rows or a NaN
+    /// value.
+    pub fn predict(&self, point: ArrayView1<F>) -> Result<L> {
+        let nearest = self.kneighbors(point, self.k)?;
+        Ok(self.vote(&nearest))
     }
 
-    fn find_dominant_class(nearest: &Vec<usize>) -> usize {
-        let mut class_counts = HashMap::<usize, usize>::new();
-        for each in nearest {
-            if let Some(class) = class_counts.get_mut(each) {
-                *class += 1;
-            } else {
-                class_counts.insert(*each, 1);
-            }
+    /// Share of the neighbours' total weight that falls on every class, in the order of
+    /// `classes`. Falls back to uniform weights if all neighbours weigh zero or the weights
+    /// add up to NaN.
+    pub fn predict_proba(&self, point: ArrayView1<F>) -> Result<Array1<f64>> {
+        let nearest = self.kneighbors(point, self.k)?;
+        let nclasses = self.labels.len();
+        let mut totals = voting::class_weights(self.weights, &nearest, &self.encoded, nclasses);
+        let sum = totals.iter().sum::<f64>();
+        if sum.is_nan() || sum <= 0.0 {
+            totals = voting::class_weights(Weights::Uniform, &nearest, &self.encoded, nclasses);
         }
+        let totals = Array1::from(totals);
+        let sum = totals.sum();
+        Ok(totals / sum)
+    }
 
-        let mut max = (0, 0);
-        for (key, value) in class_counts {
-            if value > max.1 {
-                max = (key, value)
-            }
-        }
-        max.0
+    /// Winning label among `nearest`, sorted closest first.
+    fn vote(&self, nearest: &[(usize, F)]) -> L {
+        let nclasses = self.labels.len();
+        let winner = voting::winner(
+            self.weights,
+            self.tie_break,
+            nearest,
+            &self.encoded,
+            nclasses,
+        );
+        self.labels.decode(winner).clone()
+    }
+}
+
+impl<L, F: Float, D: Distance<F>> HasSearch<F, D> for KNearest<L, F, D> {
+    fn search(&self) -> &Search<F, D> {
+        &self.search
+    }
+
+    fn search_mut(&mut self) -> &mut Search<F, D> {
+        &mut self.search
+    }
+
+    fn k(&self) -> usize {
+        self.k
+    }
+}
+
+impl<L, F: Float, D: Distance<F>> Neighbours<F, D> for KNearest<L, F, D> {}
+
+impl<L: Eq + Hash + Clone, F: Float, D: Distance<F>> Predict<F> for KNearest<L, F, D> {
+    type Output = Array1<L>;
+
+    /// Label of every row, as `predict` would give it.
+    fn predict(&self, data: ArrayView2<F>) -> Result<Array1<L>> {
+        let neighbours = self.search.kneighbors_many(data, self.k)?;
+        Ok(neighbours
+            .iter()
+            .map(|nearest| self.vote(nearest))
+            .collect())
     }
 }
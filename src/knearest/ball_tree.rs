@@ -0,0 +1,122 @@
+use super::index::{widest_axis, Candidates};
+use crate::distance::Distance;
+use crate::float::Float;
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+
+struct Node<F> {
+    center: Array1<F>,
+    /// Largest distance from `center` to a row of the node.
+    radius: F,
+    start: usize,
+    end: usize,
+    children: Option<(usize, usize)>,
+}
+
+/// Every node is a ball around the mean of its rows. Only relies on the triangle inequality,
+/// so it works with any true metric and degrades slower than a KD-tree in many dimensions.
+pub(super) struct BallTree<F> {
+    nodes: Vec<Node<F>>,
+    indexes: Vec<usize>,
+}
+
+impl<F: Float> BallTree<F> {
+    pub(super) fn new<D: Distance<F>>(data: &Array2<F>, metric: &D, leaf_size: usize) -> Self {
+        let mut tree = BallTree {
+            nodes: Vec::new(),
+            indexes: (0..data.nrows()).collect(),
+        };
+        if data.nrows() > 0 {
+            tree.build(data, metric, 0, data.nrows(), leaf_size);
+        }
+        tree
+    }
+
+    /// Builds the subtree over `indexes[start..end]` and returns its node id.
+    fn build<D: Distance<F>>(
+        &mut self,
+        data: &Array2<F>,
+        metric: &D,
+        start: usize,
+        end: usize,
+        leaf_size: usize,
+    ) -> usize {
+        let rows = &mut self.indexes[start..end];
+        let center = data
+            .select(Axis(0), rows)
+            .mean_axis(Axis(0))
+            .expect("Node is not empty");
+        let radius = rows
+            .iter()
+            .map(|i| metric.distance(center.view(), data.row(*i)))
+            .fold(F::zero(), F::max);
+
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            center,
+            radius,
+            start,
+            end,
+            children: None,
+        });
+        if end - start <= leaf_size {
+            return id;
+        }
+
+        let axis = widest_axis(data, rows);
+        let mid = rows.len() / 2;
+        rows.select_nth_unstable_by(mid, |a, b| data[[*a, axis]].total_cmp(&data[[*b, axis]]));
+
+        let left = self.build(data, metric, start, start + mid, leaf_size);
+        let right = self.build(data, metric, start + mid, end, leaf_size);
+        self.nodes[id].children = Some((left, right));
+        id
+    }
+
+    pub(super) fn nearest<D: Distance<F>>(
+        &self,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        k: usize,
+    ) -> Vec<(usize, F)> {
+        let mut candidates = Candidates::new(k);
+        if !self.nodes.is_empty() {
+            let distance = metric.distance(point, self.nodes[0].center.view());
+            self.search(0, distance, data, metric, point, &mut candidates);
+        }
+        candidates.into_sorted_vec()
+    }
+
+    /// `center_distance` is the distance from `point` to the center of `node`.
+    fn search<D: Distance<F>>(
+        &self,
+        node: usize,
+        center_distance: F,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        candidates: &mut Candidates<F>,
+    ) {
+        let node = &self.nodes[node];
+        if center_distance - node.radius > candidates.worst() {
+            return;
+        }
+
+        match node.children {
+            None => {
+                for i in &self.indexes[node.start..node.end] {
+                    candidates.push(*i, metric.distance(point, data.row(*i)));
+                }
+            }
+            Some((left, right)) => {
+                let left_distance = metric.distance(point, self.nodes[left].center.view());
+                let right_distance = metric.distance(point, self.nodes[right].center.view());
+                let mut children = [(left, left_distance), (right, right_distance)];
+                children.sort_by(|a, b| a.1.total_cmp(&b.1));
+                for (child, distance) in children {
+                    self.search(child, distance, data, metric, point, candidates);
+                }
+            }
+        }
+    }
+}
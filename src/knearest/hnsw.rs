@@ -0,0 +1,413 @@
+use super::index::{Candidate, Candidates};
+use crate::distance::Distance;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use ndarray::{Array2, ArrayView1};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256Plus;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::io::{Read, Write};
+
+/// Tuning of the HNSW (hierarchical navigable small world) graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HnswParams {
+    /// Links per node on the upper layers (twice as many on the bottom one). Higher values
+    /// improve recall at the cost of memory and build time.
+    pub m: usize,
+    /// Size of the candidate list while inserting rows; raised to `m` when smaller.
+    pub ef_construction: usize,
+    /// Size of the candidate list while querying; raised to `k` when smaller. Can be changed
+    /// on a built or loaded graph with `KNearest::ef_search`.
+    pub ef_search: usize,
+    /// Seed for the random layer assignment, so the same data gives the same graph.
+    pub seed: u64,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        HnswParams {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 50,
+            seed: 42,
+        }
+    }
+}
+
+const MAGIC: &[u8; 4] = b"HNSW";
+const VERSION: u64 = 1;
+
+/// Layered proximity graph for approximate neighbour search. `links[node][layer]` are the
+/// neighbours of a training row on one layer; a row is present on layers `0..links[row].len()`.
+pub(super) struct Hnsw {
+    params: HnswParams,
+    links: Vec<Vec<Vec<usize>>>,
+    entry_point: usize,
+}
+
+impl Hnsw {
+    pub(super) fn new<F: Float, D: Distance<F>>(
+        data: &Array2<F>,
+        metric: &D,
+        params: HnswParams,
+    ) -> Self {
+        // A candidate list shorter than `m` can't fill the links of a new row
+        let m = params.m.max(2);
+        let params = HnswParams {
+            m,
+            ef_construction: params.ef_construction.max(m),
+            ..params
+        };
+        let mut graph = Hnsw {
+            params,
+            links: Vec::with_capacity(data.nrows()),
+            entry_point: 0,
+        };
+
+        let level_factor = 1.0 / (params.m as f64).ln();
+        let mut rng = Xoshiro256Plus::seed_from_u64(params.seed);
+        for i in 0..data.nrows() {
+            let uniform: f64 = rng.gen_range(f64::EPSILON..1.0);
+            let level = (-uniform.ln() * level_factor) as usize;
+            graph.insert(data, metric, i, level);
+        }
+        graph
+    }
+
+    pub(super) fn set_ef_search(&mut self, value: usize) {
+        self.params.ef_search = value;
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            2 * self.params.m
+        } else {
+            self.params.m
+        }
+    }
+
+    fn top_layer(&self) -> usize {
+        self.links.get(self.entry_point).map_or(0, |l| l.len() - 1)
+    }
+
+    fn insert<F: Float, D: Distance<F>>(
+        &mut self,
+        data: &Array2<F>,
+        metric: &D,
+        row: usize,
+        level: usize,
+    ) {
+        self.links.push(vec![Vec::new(); level + 1]);
+        if row == 0 {
+            self.entry_point = row;
+            return;
+        }
+
+        let point = data.row(row);
+        let top_layer = self.top_layer();
+        let mut entry = vec![self.entry_point];
+        for layer in (level + 1..=top_layer).rev() {
+            let closest = self.search_layer(data, metric, point, &entry, 1, layer);
+            entry = vec![closest[0].0];
+        }
+
+        for layer in (0..=level.min(top_layer)).rev() {
+            let found = self.search_layer(
+                data,
+                metric,
+                point,
+                &entry,
+                self.params.ef_construction,
+                layer,
+            );
+            let max_links = self.max_links(layer);
+            let neighbours = select_neighbours(data, metric, &found, self.params.m);
+            for neighbour in neighbours {
+                self.links[row][layer].push(neighbour);
+                self.links[neighbour][layer].push(row);
+                if self.links[neighbour][layer].len() > max_links {
+                    self.prune(data, metric, neighbour, layer, max_links);
+                }
+            }
+            entry = found.into_iter().map(|(i, _)| i).collect();
+        }
+
+        if level > top_layer {
+            self.entry_point = row;
+        }
+    }
+
+    /// Reduces the links of `node` on `layer` to at most `max_links`, chosen like the links
+    /// of a new row.
+    fn prune<F: Float, D: Distance<F>>(
+        &mut self,
+        data: &Array2<F>,
+        metric: &D,
+        node: usize,
+        layer: usize,
+        max_links: usize,
+    ) {
+        let mut candidates: Vec<(usize, F)> = self.links[node][layer]
+            .iter()
+            .map(|i| (*i, metric.distance(data.row(node), data.row(*i))))
+            .collect();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        self.links[node][layer] = select_neighbours(data, metric, &candidates, max_links);
+    }
+
+    /// Best-first search of one layer, returning up to `ef` rows closest first.
+    fn search_layer<F: Float, D: Distance<F>>(
+        &self,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        entry: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<(usize, F)> {
+        let mut visited: HashSet<usize> = entry.iter().copied().collect();
+        let mut to_visit = BinaryHeap::new();
+        let mut found = Candidates::new(ef);
+        for i in entry {
+            let distance = metric.distance(point, data.row(*i));
+            to_visit.push(Reverse(Candidate {
+                distance,
+                index: *i,
+            }));
+            found.push(*i, distance);
+        }
+
+        while let Some(Reverse(current)) = to_visit.pop() {
+            if current.distance > found.worst() {
+                break;
+            }
+            for neighbour in &self.links[current.index][layer] {
+                if !visited.insert(*neighbour) {
+                    continue;
+                }
+                let distance = metric.distance(point, data.row(*neighbour));
+                if distance <= found.worst() {
+                    to_visit.push(Reverse(Candidate {
+                        distance,
+                        index: *neighbour,
+                    }));
+                    found.push(*neighbour, distance);
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    pub(super) fn nearest<F: Float, D: Distance<F>>(
+        &self,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        k: usize,
+    ) -> Vec<(usize, F)> {
+        if self.links.is_empty() {
+            return Vec::new();
+        }
+
+        let mut entry = self.entry_point;
+        for layer in (1..=self.top_layer()).rev() {
+            entry = self.search_layer(data, metric, point, &[entry], 1, layer)[0].0;
+        }
+        let ef = self.params.ef_search.max(k);
+        let mut found = self.search_layer(data, metric, point, &[entry], ef, 0);
+        found.truncate(k);
+        found
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Little-endian binary layout: magic, version, params, entry point, node count, then
+    /// for every node its layer count and, per layer, the link count and links.
+    pub(super) fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(MAGIC)?;
+        let params = &self.params;
+        let header = [
+            VERSION,
+            params.m as u64,
+            params.ef_construction as u64,
+            params.ef_search as u64,
+            params.seed,
+            self.entry_point as u64,
+            self.links.len() as u64,
+        ];
+        for value in header {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+
+        for layers in &self.links {
+            writer.write_all(&(layers.len() as u64).to_le_bytes())?;
+            for links in layers {
+                writer.write_all(&(links.len() as u64).to_le_bytes())?;
+                for link in links {
+                    writer.write_all(&(*link as u64).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u64(reader)? != VERSION {
+            return Err(Error::InvalidIndex);
+        }
+
+        let params = HnswParams {
+            m: read_usize(reader)?,
+            ef_construction: read_usize(reader)?,
+            ef_search: read_usize(reader)?,
+            seed: read_u64(reader)?,
+        };
+        let entry_point = read_usize(reader)?;
+        let n_nodes = read_usize(reader)?;
+
+        let mut links = Vec::new();
+        for _ in 0..n_nodes {
+            let mut layers = Vec::new();
+            for _ in 0..read_usize(reader)? {
+                let mut layer = Vec::new();
+                for _ in 0..read_usize(reader)? {
+                    let link = read_usize(reader)?;
+                    if link >= n_nodes {
+                        return Err(Error::InvalidIndex);
+                    }
+                    layer.push(link);
+                }
+                layers.push(layer);
+            }
+            if layers.is_empty() {
+                return Err(Error::InvalidIndex);
+            }
+            links.push(layers);
+        }
+        // Links may only point to rows that are present on the same layer
+        let valid = links.iter().all(|layers| {
+            layers
+                .iter()
+                .enumerate()
+                .all(|(layer, neighbours)| neighbours.iter().all(|n| links[*n].len() > layer))
+        });
+        if !valid || (n_nodes > 0 && entry_point >= n_nodes) {
+            return Err(Error::InvalidIndex);
+        }
+
+        Ok(Hnsw {
+            params,
+            links,
+            entry_point,
+        })
+    }
+}
+
+/// Neighbour selection heuristic of the HNSW paper (Malkov & Yashunin, algorithm 4) without
+/// extending or refilling the candidates: walking `candidates` (`(row, distance to the new
+/// node)`, closest first), a row is linked only if it is closer to the new node than to every
+/// row linked so far. Links then point in different directions instead of all into the
+/// closest cluster, which keeps clustered data connected.
+fn select_neighbours<F: Float, D: Distance<F>>(
+    data: &Array2<F>,
+    metric: &D,
+    candidates: &[(usize, F)],
+    max_links: usize,
+) -> Vec<usize> {
+    let mut selected: Vec<usize> = Vec::with_capacity(max_links);
+    for (candidate, distance) in candidates {
+        if selected.len() >= max_links {
+            break;
+        }
+        let diverse = selected
+            .iter()
+            .all(|s| metric.distance(data.row(*candidate), data.row(*s)) > *distance);
+        if diverse {
+            selected.push(*candidate);
+        }
+    }
+    selected
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_usize<R: Read>(reader: &mut R) -> Result<usize> {
+    usize::try_from(read_u64(reader)?).map_err(|_| Error::InvalidIndex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::Euclidean;
+    use crate::knearest::index::Index;
+
+    fn random_rows(seed: u64, nrows: usize) -> Array2<f64> {
+        let mut rng = Xoshiro256Plus::seed_from_u64(seed);
+        Array2::from_shape_fn((nrows, 8), |_| rng.gen_range(-10.0..10.0))
+    }
+
+    /// Share of the true `k` nearest neighbours of `queries` that `graph` finds.
+    fn recall(graph: &Hnsw, data: &Array2<f64>, queries: &Array2<f64>, k: usize) -> f64 {
+        let mut found = 0;
+        for point in queries.rows() {
+            let approximate = graph.nearest(data, &Euclidean, point, k);
+            assert_eq!(approximate.len(), k);
+            let exact = Index::BruteForce.nearest(data, &Euclidean, point, k);
+            found += exact
+                .iter()
+                .filter(|(i, _)| approximate.iter().any(|(j, _)| i == j))
+                .count();
+        }
+        found as f64 / (queries.nrows() * k) as f64
+    }
+
+    #[test]
+    fn default_params_find_most_neighbours() {
+        let data = random_rows(1, 1000);
+        let queries = random_rows(2, 100);
+        let graph = Hnsw::new(&data, &Euclidean, HnswParams::default());
+        assert!(recall(&graph, &data, &queries, 10) > 0.95);
+    }
+
+    #[test]
+    fn short_construction_list_still_links_rows() {
+        let data = random_rows(1, 1000);
+        let queries = random_rows(2, 100);
+        let params = HnswParams {
+            ef_construction: 0,
+            ..HnswParams::default()
+        };
+        let graph = Hnsw::new(&data, &Euclidean, params);
+        assert!(recall(&graph, &data, &queries, 10) > 0.9);
+    }
+
+    #[test]
+    fn saved_graph_gives_the_same_neighbours() {
+        let data = random_rows(1, 500);
+        let queries = random_rows(2, 50);
+        let graph = Hnsw::new(&data, &Euclidean, HnswParams::default());
+        let mut bytes = Vec::new();
+        graph.write(&mut bytes).unwrap();
+        let loaded = Hnsw::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.params, graph.params);
+        for point in queries.rows() {
+            assert_eq!(
+                loaded.nearest(&data, &Euclidean, point, 5),
+                graph.nearest(&data, &Euclidean, point, 5)
+            );
+        }
+        assert!(matches!(
+            Hnsw::read(&mut &bytes[..bytes.len() - 1]),
+            Err(Error::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+use super::ball_tree::BallTree;
+use super::hnsw::{Hnsw, HnswParams};
+use super::kd_tree::KdTree;
+use super::vp_tree::VpTree;
+use crate::distance::Distance;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use ndarray::{Array2, ArrayView1};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Search structure `KNearest` uses to find neighbours.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IndexKind {
+    /// Measures the distance to every training row.
+    #[default]
+    BruteForce,
+    /// Splits the space along one column at a time. Exact and sublinear for low-dimensional
+    /// data; needs a metric that can be bounded per column (see `Distance::axis_bound`).
+    KdTree { leaf_size: usize },
+    /// Nested balls around the mean of their rows. Works with any metric that satisfies the
+    /// triangle inequality and copes better with many dimensions than a KD-tree.
+    BallTree { leaf_size: usize },
+    /// Splits rows by their distance to a training row. Works with any metric that
+    /// satisfies the triangle inequality.
+    VpTree { leaf_size: usize },
+    /// Approximate search over a layered proximity graph. Works with any metric and scales
+    /// to large, high-dimensional training sets, but may miss some true neighbours.
+    Hnsw(HnswParams),
+}
+
+pub(super) enum Index<F = f64> {
+    BruteForce,
+    KdTree(KdTree<F>),
+    BallTree(BallTree<F>),
+    VpTree(VpTree<F>),
+    Hnsw(Hnsw),
+}
+
+impl<F: Float> Index<F> {
+    pub(super) fn build<D: Distance<F>>(
+        kind: IndexKind,
+        data: &Array2<F>,
+        metric: &D,
+    ) -> Result<Self> {
+        metric.check_columns(data.ncols())?;
+        match kind {
+            IndexKind::BruteForce => Ok(Index::BruteForce),
+            IndexKind::KdTree { leaf_size } => {
+                if (0..data.ncols()).any(|axis| metric.axis_bound(axis, F::zero()).is_none()) {
+                    return Err(Error::UnsupportedMetric);
+                }
+                Ok(Index::KdTree(KdTree::new(data, leaf_size.max(1))))
+            }
+            IndexKind::BallTree { leaf_size } => {
+                if !metric.satisfies_triangle_inequality() {
+                    return Err(Error::UnsupportedMetric);
+                }
+                Ok(Index::BallTree(BallTree::new(
+                    data,
+                    metric,
+                    leaf_size.max(1),
+                )))
+            }
+            IndexKind::VpTree { leaf_size } => {
+                if !metric.satisfies_triangle_inequality() {
+                    return Err(Error::UnsupportedMetric);
+                }
+                Ok(Index::VpTree(VpTree::new(data, metric, leaf_size.max(1))))
+            }
+            IndexKind::Hnsw(params) => Ok(Index::Hnsw(Hnsw::new(data, metric, params))),
+        }
+    }
+
+    /// The `k` training rows closest to `point` as `(row, distance)`, closest first. Rows at
+    /// the same distance are ordered by index, like a stable sort of all distances.
+    pub(super) fn nearest<D: Distance<F>>(
+        &self,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        k: usize,
+    ) -> Vec<(usize, F)> {
+        match self {
+            Index::BruteForce => {
+                let mut candidates = Candidates::new(k);
+                for (i, row) in data.rows().into_iter().enumerate() {
+                    candidates.push(i, metric.distance(point, row));
+                }
+                candidates.into_sorted_vec()
+            }
+            Index::KdTree(tree) => tree.nearest(data, metric, point, k),
+            Index::BallTree(tree) => tree.nearest(data, metric, point, k),
+            Index::VpTree(tree) => tree.nearest(data, metric, point, k),
+            Index::Hnsw(graph) => graph.nearest(data, metric, point, k),
+        }
+    }
+}
+
+/// Column in which the given training rows have the widest spread.
+pub(super) fn widest_axis<F: Float>(data: &Array2<F>, rows: &[usize]) -> usize {
+    (0..data.ncols())
+        .map(|axis| {
+            let (min, max) = rows
+                .iter()
+                .fold((F::infinity(), F::neg_infinity()), |acc, i| {
+                    (acc.0.min(data[[*i, axis]]), acc.1.max(data[[*i, axis]]))
+                });
+            (axis, max - min)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(axis, _)| axis)
+}
+
+/// Keeps the `k` best `(row, distance)` pairs seen so far.
+pub(super) struct Candidates<F = f64> {
+    k: usize,
+    heap: BinaryHeap<Candidate<F>>,
+}
+
+impl<F: Float> Candidates<F> {
+    pub(super) fn new(k: usize) -> Self {
+        Candidates {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    pub(super) fn push(&mut self, index: usize, distance: F) {
+        if self.k == 0 {
+            return;
+        }
+        let candidate = Candidate { distance, index };
+        if self.heap.len() < self.k {
+            self.heap.push(candidate);
+        } else if candidate < *self.heap.peek().expect("Heap is full") {
+            self.heap.pop();
+            self.heap.push(candidate);
+        }
+    }
+
+    /// Distance a row has to beat (or tie) to be kept.
+    pub(super) fn worst(&self) -> F {
+        if self.heap.len() < self.k {
+            F::infinity()
+        } else {
+            self.heap.peek().map_or(F::infinity(), |c| c.distance)
+        }
+    }
+
+    pub(super) fn into_sorted_vec(self) -> Vec<(usize, F)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|c| (c.index, c.distance))
+            .collect()
+    }
+}
+
+pub(super) struct Candidate<F = f64> {
+    pub(super) distance: F,
+    pub(super) index: usize,
+}
+
+impl<F: Float> PartialEq for Candidate<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F: Float> Eq for Candidate<F> {}
+
+impl<F: Float> PartialOrd for Candidate<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: Float> Ord for Candidate<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::{Euclidean, Manhattan};
+    use approx::assert_abs_diff_eq;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256Plus;
+
+    fn random_rows(seed: u64, nrows: usize) -> Array2<f64> {
+        let mut rng = Xoshiro256Plus::seed_from_u64(seed);
+        Array2::from_shape_fn((nrows, 3), |_| rng.gen_range(-10.0..10.0))
+    }
+
+    /// Every exact tree finds the same neighbours, in the same order, as a full scan.
+    fn assert_trees_match_brute_force<D: Distance<f64>>(metric: &D) {
+        let data = random_rows(1, 500);
+        let queries = random_rows(2, 50);
+        let kinds = [
+            IndexKind::KdTree { leaf_size: 4 },
+            IndexKind::BallTree { leaf_size: 4 },
+            IndexKind::VpTree { leaf_size: 4 },
+        ];
+        for kind in kinds {
+            let index = Index::build(kind, &data, metric).unwrap();
+            for point in queries.rows() {
+                for k in [1, 7, 30] {
+                    let expected = Index::BruteForce.nearest(&data, metric, point, k);
+                    let found = index.nearest(&data, metric, point, k);
+                    assert_eq!(found.len(), k, "{:?}", kind);
+                    for ((i, a), (j, b)) in found.iter().zip(&expected) {
+                        assert_eq!(i, j, "{:?}", kind);
+                        assert_abs_diff_eq!(a, b, epsilon = 1e-12);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn trees_match_brute_force_euclidean() {
+        assert_trees_match_brute_force(&Euclidean);
+    }
+
+    #[test]
+    fn trees_match_brute_force_manhattan() {
+        assert_trees_match_brute_force(&Manhattan);
+    }
+}
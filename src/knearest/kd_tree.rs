@@ -0,0 +1,110 @@
+use super::index::{widest_axis, Candidates};
+use crate::distance::Distance;
+use crate::float::Float;
+use ndarray::{Array2, ArrayView1};
+
+enum Node<F> {
+    Leaf {
+        start: usize,
+        end: usize,
+    },
+    Split {
+        axis: usize,
+        value: F,
+        left: usize,
+        right: usize,
+    },
+}
+
+/// Nodes refer to training rows through `indexes`, so the tree never copies the data.
+pub(super) struct KdTree<F> {
+    nodes: Vec<Node<F>>,
+    indexes: Vec<usize>,
+}
+
+impl<F: Float> KdTree<F> {
+    pub(super) fn new(data: &Array2<F>, leaf_size: usize) -> Self {
+        let mut tree = KdTree {
+            nodes: Vec::new(),
+            indexes: (0..data.nrows()).collect(),
+        };
+        tree.build(data, 0, data.nrows(), leaf_size);
+        tree
+    }
+
+    /// Builds the subtree over `indexes[start..end]` and returns its node id.
+    fn build(&mut self, data: &Array2<F>, start: usize, end: usize, leaf_size: usize) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node::Leaf { start, end });
+        if end - start <= leaf_size {
+            return id;
+        }
+
+        // Split along the column with the widest spread, at its median
+        let rows = &mut self.indexes[start..end];
+        let axis = widest_axis(data, rows);
+        let mid = rows.len() / 2;
+        rows.select_nth_unstable_by(mid, |a, b| data[[*a, axis]].total_cmp(&data[[*b, axis]]));
+        let value = data[[rows[mid], axis]];
+
+        let left = self.build(data, start, start + mid, leaf_size);
+        let right = self.build(data, start + mid, end, leaf_size);
+        self.nodes[id] = Node::Split {
+            axis,
+            value,
+            left,
+            right,
+        };
+        id
+    }
+
+    pub(super) fn nearest<D: Distance<F>>(
+        &self,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        k: usize,
+    ) -> Vec<(usize, F)> {
+        let mut candidates = Candidates::new(k);
+        if !self.indexes.is_empty() {
+            self.search(0, data, metric, point, &mut candidates);
+        }
+        candidates.into_sorted_vec()
+    }
+
+    fn search<D: Distance<F>>(
+        &self,
+        node: usize,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        candidates: &mut Candidates<F>,
+    ) {
+        match self.nodes[node] {
+            Node::Leaf { start, end } => {
+                for i in &self.indexes[start..end] {
+                    candidates.push(*i, metric.distance(point, data.row(*i)));
+                }
+            }
+            Node::Split {
+                axis,
+                value,
+                left,
+                right,
+            } => {
+                let diff = point[axis] - value;
+                let (near, far) = if diff < F::zero() {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+                self.search(near, data, metric, point, candidates);
+
+                let bound = metric.axis_bound(axis, diff.abs()).unwrap_or(F::zero());
+                if bound <= candidates.worst() {
+                    self.search(far, data, metric, point, candidates);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Maps arbitrary labels to positions `0..len()` in order of their first appearance, and
+/// back.
+#[derive(Clone, Debug)]
+pub struct LabelEncoder<L> {
+    classes: Vec<L>,
+    positions: HashMap<L, usize>,
+}
+
+impl<L: Eq + Hash + Clone> LabelEncoder<L> {
+    pub fn fit(labels: &[L]) -> Self {
+        let mut classes = Vec::new();
+        let mut positions = HashMap::new();
+        for label in labels {
+            if !positions.contains_key(label) {
+                positions.insert(label.clone(), classes.len());
+                classes.push(label.clone());
+            }
+        }
+        LabelEncoder { classes, positions }
+    }
+
+    /// Distinct labels; the position of a label here is its encoding.
+    pub fn classes(&self) -> &[L] {
+        &self.classes
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Position of `label`, or `None` if it wasn't among the fitted labels.
+    pub fn encode(&self, label: &L) -> Option<usize> {
+        self.positions.get(label).copied()
+    }
+
+    /// Label at `position`. Panics if `position` is not below `len()`.
+    pub fn decode(&self, position: usize) -> &L {
+        &self.classes[position]
+    }
+
+    /// Positions of all `labels`, or `None` if any of them wasn't among the fitted labels.
+    pub fn transform(&self, labels: &[L]) -> Option<Vec<usize>> {
+        labels.iter().map(|label| self.encode(label)).collect()
+    }
+}
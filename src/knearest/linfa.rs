@@ -0,0 +1,190 @@
+use super::{Aggregate, KNearest, KNearestRegressor, TieBreak, Weights};
+use crate::distance::Distance;
+use crate::error::{Error, Result};
+use crate::float::Float;
+use crate::traits::Predict;
+use ::linfa::dataset::AsSingleTargets;
+use ::linfa::traits::{Fit, PredictInplace};
+use ::linfa::{DatasetBase, ParamGuard};
+use ndarray::{Array1, ArrayBase, Data, Ix2};
+use std::hash::Hash;
+
+/// Checked `KNearest` settings; fitting stores the records and targets of the dataset.
+#[derive(Clone, Copy, Debug)]
+pub struct KNearestValidParams {
+    k: usize,
+    weights: Weights,
+    tie_break: TieBreak,
+}
+
+/// Unchecked `KNearest` settings for linfa: `fit` validates them first, so they can be passed
+/// to linfa's cross-validation and metrics tooling. The fitted model predicts batches through
+/// linfa's `Predict`; call it as `Predict::predict(&model, &records)`, because the inherent
+/// `predict` takes a single sample.
+#[derive(Clone, Copy, Debug)]
+pub struct KNearestParams(KNearestValidParams);
+
+impl KNearestParams {
+    pub fn new(k: usize) -> Self {
+        KNearestParams(KNearestValidParams {
+            k,
+            weights: Weights::default(),
+            tie_break: TieBreak::default(),
+        })
+    }
+
+    pub fn weights(mut self, value: Weights) -> Self {
+        self.0.weights = value;
+        self
+    }
+
+    pub fn tie_break(mut self, value: TieBreak) -> Self {
+        self.0.tie_break = value;
+        self
+    }
+}
+
+impl ParamGuard for KNearestParams {
+    type Checked = KNearestValidParams;
+    type Error = Error;
+
+    fn check_ref(&self) -> Result<&KNearestValidParams> {
+        check_k(self.0.k)?;
+        Ok(&self.0)
+    }
+
+    fn check(self) -> Result<KNearestValidParams> {
+        self.check_ref()?;
+        Ok(self.0)
+    }
+}
+
+impl<L, F, S, T> Fit<ArrayBase<S, Ix2>, T, Error> for KNearestValidParams
+where
+    L: Eq + Hash + Clone,
+    F: Float,
+    S: Data<Elem = F>,
+    T: AsSingleTargets<Elem = L>,
+{
+    type Object = KNearest<L, F>;
+
+    fn fit(&self, dataset: &DatasetBase<ArrayBase<S, Ix2>, T>) -> Result<KNearest<L, F>> {
+        let labels = dataset.targets.as_single_targets().to_vec();
+        Ok(KNearest::new(self.k, dataset.records.to_owned(), labels)?
+            .weights(self.weights)
+            .tie_break(self.tie_break))
+    }
+}
+
+impl<L, F, D, S> PredictInplace<ArrayBase<S, Ix2>, Array1<L>> for KNearest<L, F, D>
+where
+    L: Eq + Hash + Clone,
+    F: Float,
+    D: Distance<F>,
+    S: Data<Elem = F>,
+{
+    /// Panics if `x` has a different number of columns than the training rows or NaN
+    /// values, or `y` a different length than `x` has rows.
+    fn predict_inplace(&self, x: &ArrayBase<S, Ix2>, y: &mut Array1<L>) {
+        assert_eq!(x.nrows(), y.len(), "Targets match the records");
+        let labels = Predict::predict(self, x.view()).expect("Records are valid queries");
+        y.assign(&labels);
+    }
+
+    fn default_target(&self, x: &ArrayBase<S, Ix2>) -> Array1<L> {
+        Array1::from_elem(x.nrows(), self.labels.decode(0).clone())
+    }
+}
+
+/// Checked `KNearestRegressor` settings; fitting stores the records and targets of the
+/// dataset.
+#[derive(Clone, Copy, Debug)]
+pub struct KNearestRegressorValidParams {
+    k: usize,
+    weights: Weights,
+    aggregate: Aggregate,
+}
+
+/// Unchecked `KNearestRegressor` settings for linfa, see `KNearestParams`.
+#[derive(Clone, Copy, Debug)]
+pub struct KNearestRegressorParams(KNearestRegressorValidParams);
+
+impl KNearestRegressorParams {
+    pub fn new(k: usize) -> Self {
+        KNearestRegressorParams(KNearestRegressorValidParams {
+            k,
+            weights: Weights::default(),
+            aggregate: Aggregate::default(),
+        })
+    }
+
+    pub fn weights(mut self, value: Weights) -> Self {
+        self.0.weights = value;
+        self
+    }
+
+    pub fn aggregate(mut self, value: Aggregate) -> Self {
+        self.0.aggregate = value;
+        self
+    }
+}
+
+impl ParamGuard for KNearestRegressorParams {
+    type Checked = KNearestRegressorValidParams;
+    type Error = Error;
+
+    fn check_ref(&self) -> Result<&KNearestRegressorValidParams> {
+        check_k(self.0.k)?;
+        Ok(&self.0)
+    }
+
+    fn check(self) -> Result<KNearestRegressorValidParams> {
+        self.check_ref()?;
+        Ok(self.0)
+    }
+}
+
+impl<F, S, T> Fit<ArrayBase<S, Ix2>, T, Error> for KNearestRegressorValidParams
+where
+    F: Float,
+    S: Data<Elem = F>,
+    T: AsSingleTargets<Elem = F>,
+{
+    type Object = KNearestRegressor<F>;
+
+    fn fit(&self, dataset: &DatasetBase<ArrayBase<S, Ix2>, T>) -> Result<KNearestRegressor<F>> {
+        let targets = dataset.targets.as_single_targets().to_vec();
+        Ok(
+            KNearestRegressor::new(self.k, dataset.records.to_owned(), targets)?
+                .weights(self.weights)
+                .aggregate(self.aggregate),
+        )
+    }
+}
+
+impl<F, D, S> PredictInplace<ArrayBase<S, Ix2>, Array1<F>> for KNearestRegressor<F, D>
+where
+    F: Float,
+    D: Distance<F>,
+    S: Data<Elem = F>,
+{
+    /// Panics if `x` has a different number of columns than the training rows or NaN
+    /// values, or `y` a different length than `x` has rows.
+    fn predict_inplace(&self, x: &ArrayBase<S, Ix2>, y: &mut Array1<F>) {
+        assert_eq!(x.nrows(), y.len(), "Targets match the records");
+        let targets = Predict::predict(self, x.view()).expect("Records are valid queries");
+        y.assign(&targets);
+    }
+
+    fn default_target(&self, x: &ArrayBase<S, Ix2>) -> Array1<F> {
+        Array1::zeros(x.nrows())
+    }
+}
+
+/// `k` has to be positive; whether it exceeds the training rows is only known in `fit`.
+fn check_k(k: usize) -> Result<()> {
+    if k == 0 {
+        return Err(Error::InvalidParameter("k must be positive".to_owned()));
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use super::search::HasSearch;
+use super::IndexKind;
+use crate::distance::Distance;
+use crate::error::Result;
+use crate::float::Float;
+use ndarray::{ArrayBase, ArrayView1, Data, Ix2};
+use std::io::{Read, Write};
+
+/// Neighbour queries and index management shared by `KNearest` and `KNearestRegressor`.
+/// Bring it into scope to call these methods; it can't be implemented outside this crate.
+pub trait Neighbours<F: Float, D: Distance<F>>: HasSearch<F, D> {
+    /// Builds the search structure used by `predict`. Call it after choosing the metric.
+    /// Per-class metrics only work with `IndexKind::BruteForce`.
+    fn build_index(mut self, kind: IndexKind) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        self.search_mut().build_index(kind)?;
+        Ok(self)
+    }
+
+    /// Size of the candidate list of the built or loaded HNSW graph while querying; saved
+    /// along with the graph. Fails if the index isn't HNSW.
+    fn ef_search(mut self, value: usize) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        self.search_mut().ef_search(value)?;
+        Ok(self)
+    }
+
+    /// Writes the built HNSW graph, so `load_index` can restore it without rebuilding.
+    fn save_index<W: Write>(&self, writer: W) -> Result<()> {
+        self.search().save_index(writer)
+    }
+
+    /// Restores a graph written by `save_index`. It has to come from the same training data
+    /// and metric; only the number of rows can be checked.
+    fn load_index<R: Read>(mut self, reader: R) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        self.search_mut().load_index(reader)?;
+        Ok(self)
+    }
+
+    /// Share of the true `k` nearest neighbours of `queries` (one per row) that the current
+    /// index finds, from `0.0` to `1.0`. Always `1.0` for exact indexes.
+    fn recall<S: Data<Elem = F>>(&self, queries: &ArrayBase<S, Ix2>) -> Result<f64> {
+        self.search().recall(queries, self.k())
+    }
+
+    /// The `k` training rows closest to `point` as `(row, distance)`, closest first; rows
+    /// at equal distance are ordered by row. Approximate when the index is HNSW. Fails with
+    /// `Error::InvalidK` unless `k` is between one and the number of training rows.
+    fn kneighbors(&self, point: ArrayView1<F>, k: usize) -> Result<Vec<(usize, F)>> {
+        self.search().kneighbors(point, k)
+    }
+
+    /// `kneighbors` for every row of `points`.
+    fn kneighbors_many<S: Data<Elem = F>>(
+        &self,
+        points: &ArrayBase<S, Ix2>,
+        k: usize,
+    ) -> Result<Vec<Vec<(usize, F)>>> {
+        self.search().kneighbors_many(points.view(), k)
+    }
+
+    /// All training rows within `radius` of `point` (inclusive) as `(row, distance)`, in the
+    /// order of `kneighbors`. Scans every training row regardless of the index.
+    fn radius_neighbors(&self, point: ArrayView1<F>, radius: F) -> Result<Vec<(usize, F)>> {
+        self.search().radius_neighbors(point, radius)
+    }
+}
@@ -0,0 +1,163 @@
+use super::search::{HasSearch, Search};
+use super::voting::{self, Weights};
+use super::Neighbours;
+use crate::distance::{Distance, Euclidean, Mahalanobis};
+use crate::error::Result;
+use crate::float::Float;
+use crate::traits::Predict;
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use std::sync::Arc;
+
+/// How `KNearestRegressor` combines the targets of the nearest neighbours.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Weighted mean of the targets.
+    #[default]
+    Mean,
+    /// Weighted median of the targets; the mean of the two middle targets when the weight
+    /// splits exactly between them.
+    Median,
+}
+
+/// Predicts a continuous target from the targets of the `k` nearest training rows.
+pub struct KNearestRegressor<F = f64, D = Euclidean> {
+    k: usize,
+    targets: Vec<F>,
+    weights: Weights,
+    aggregate: Aggregate,
+    search: Search<F, D>,
+}
+
+impl<F: Float> KNearestRegressor<F> {
+    /// `targets` holds the target of every row of `data`. Fails if `data` is empty or has
+    /// NaN values, the lengths differ or `k` isn't between one and the number of rows.
+    /// See `KNearest::new` for sharing `data`.
+    pub fn new(k: usize, data: impl Into<Arc<Array2<F>>>, targets: Vec<F>) -> Result<Self> {
+        let search = Search::new(data.into(), targets.len(), k)?;
+        Ok(KNearestRegressor {
+            k,
+            targets,
+            weights: Weights::default(),
+            aggregate: Aggregate::default(),
+            search,
+        })
+    }
+}
+
+impl<F: Float, D: Distance<F>> KNearestRegressor<F, D> {
+    /// Replaces the metric; any index built so far is dropped.
+    pub fn with_metric<M: Distance<F>>(self, metric: M) -> KNearestRegressor<F, M> {
+        KNearestRegressor {
+            k: self.k,
+            targets: self.targets,
+            weights: self.weights,
+            aggregate: self.aggregate,
+            search: self.search.with_metric(metric),
+        }
+    }
+
+    pub fn weights(mut self, value: Weights) -> Self {
+        self.weights = value;
+        self
+    }
+
+    pub fn aggregate(mut self, value: Aggregate) -> Self {
+        self.aggregate = value;
+        self
+    }
+
+    /// Switches to a Mahalanobis metric with the covariance estimated from the training data.
+    /// See `Mahalanobis::fit` for `shrinkage`.
+    pub fn mahalanobis(self, shrinkage: f64) -> Result<KNearestRegressor<F, Mahalanobis<F>>> {
+        let metric = Mahalanobis::fit(self.search.data(), shrinkage)?;
+        Ok(self.with_metric(metric))
+    }
+
+    /// Combines the targets of the `k` nearest neighbours; falls back to uniform weights if
+    /// all neighbours weigh zero or the weights add up to NaN. Fails if `point` has a
+    /// different number of values than the training rows or a NaN value.
+    pub fn predict(&self, point: ArrayView1<F>) -> Result<F> {
+        let nearest = self.kneighbors(point, self.k)?;
+        Ok(self.combine(&nearest))
+    }
+
+    /// Aggregated target of `nearest`, computed in `f64`.
+    fn combine(&self, nearest: &[(usize, F)]) -> F {
+        let mut weights = voting::neighbour_weights(self.weights, nearest);
+        let sum = weights.iter().sum::<f64>();
+        if sum.is_nan() || sum <= 0.0 {
+            weights = voting::neighbour_weights(Weights::Uniform, nearest);
+        }
+        let weighted: Vec<(f64, f64)> = nearest
+            .iter()
+            .zip(weights)
+            .map(|((i, _), weight)| {
+                (
+                    self.targets[*i].to_f64().expect("Float converts to f64"),
+                    weight,
+                )
+            })
+            .collect();
+        F::cast(match self.aggregate {
+            Aggregate::Mean => weighted_mean(&weighted),
+            Aggregate::Median => weighted_median(weighted),
+        })
+    }
+}
+
+impl<F: Float, D: Distance<F>> HasSearch<F, D> for KNearestRegressor<F, D> {
+    fn search(&self) -> &Search<F, D> {
+        &self.search
+    }
+
+    fn search_mut(&mut self) -> &mut Search<F, D> {
+        &mut self.search
+    }
+
+    fn k(&self) -> usize {
+        self.k
+    }
+}
+
+impl<F: Float, D: Distance<F>> Neighbours<F, D> for KNearestRegressor<F, D> {}
+
+impl<F: Float, D: Distance<F>> Predict<F> for KNearestRegressor<F, D> {
+    type Output = Array1<F>;
+
+    /// Target of every row, as `predict` would give it.
+    fn predict(&self, data: ArrayView2<F>) -> Result<Array1<F>> {
+        let neighbours = self.search.kneighbors_many(data, self.k)?;
+        Ok(neighbours
+            .iter()
+            .map(|nearest| self.combine(nearest))
+            .collect())
+    }
+}
+
+/// Mean of `(target, weight)` pairs.
+fn weighted_mean(weighted: &[(f64, f64)]) -> f64 {
+    let total: f64 = weighted.iter().map(|(_, weight)| weight).sum();
+    weighted
+        .iter()
+        .map(|(target, weight)| target * weight)
+        .sum::<f64>()
+        / total
+}
+
+/// Target at which the cumulative weight of the sorted `(target, weight)` pairs reaches half.
+fn weighted_median(mut weighted: Vec<(f64, f64)>) -> f64 {
+    weighted.retain(|(_, weight)| *weight > 0.0);
+    weighted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let half = weighted.iter().map(|(_, weight)| weight).sum::<f64>() / 2.0;
+    let mut cumulative = 0.0;
+    for (i, (target, weight)) in weighted.iter().enumerate() {
+        cumulative += weight;
+        if cumulative == half && i + 1 < weighted.len() {
+            return (target + weighted[i + 1].0) / 2.0;
+        }
+        if cumulative >= half {
+            return *target;
+        }
+    }
+    f64::NAN
+}
@@ -0,0 +1,273 @@
+use super::hnsw::Hnsw;
+use super::index::{Candidates, Index, IndexKind};
+use crate::distance::{Distance, Euclidean, Mahalanobis};
+use crate::error::{check_nan, Error, Result};
+use crate::float::Float;
+use crate::parallel::map_indexes;
+use ndarray::{Array2, ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix2};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+/// Training rows with the metric and index used to find their neighbours; shared by
+/// `KNearest` and `KNearestRegressor`. The rows sit behind an `Arc`, so several models can
+/// be built on the same table without copying it.
+pub struct Search<F = f64, D = Euclidean> {
+    data: Arc<Array2<F>>,
+    metric: D,
+    /// Group of every training row and a metric per group; when set, the metric of a row's
+    /// group replaces `metric` for that row.
+    group_metrics: Option<(Vec<usize>, Vec<Mahalanobis<F>>)>,
+    index: Index<F>,
+}
+
+/// Access to the `Search` of a model, which `Neighbours` builds on. Public only so it can
+/// bound `Neighbours`; this module is private, so the trait can't be implemented elsewhere.
+pub trait HasSearch<F, D> {
+    fn search(&self) -> &Search<F, D>;
+
+    fn search_mut(&mut self) -> &mut Search<F, D>;
+
+    /// Number of neighbours the model predicts from.
+    fn k(&self) -> usize;
+}
+
+impl<F: Float> Search<F> {
+    /// Checks that `data` has rows without NaN values, that `ntargets` (labels or targets)
+    /// matches them and that `k` is between one and the number of rows.
+    pub(super) fn new(data: Arc<Array2<F>>, ntargets: usize, k: usize) -> Result<Self> {
+        if data.nrows() == 0 {
+            return Err(Error::EmptyDataset);
+        }
+        if ntargets != data.nrows() {
+            return Err(Error::LengthMismatch {
+                expected: data.nrows(),
+                found: ntargets,
+            });
+        }
+        check_k(k, data.nrows())?;
+        check_nan(data.as_ref())?;
+        Ok(Search {
+            data,
+            metric: Euclidean,
+            group_metrics: None,
+            index: Index::BruteForce,
+        })
+    }
+}
+
+impl<F: Float, D: Distance<F>> Search<F, D> {
+    pub(super) fn data(&self) -> &Arc<Array2<F>> {
+        &self.data
+    }
+
+    pub(super) fn with_metric<M: Distance<F>>(self, metric: M) -> Search<F, M> {
+        Search {
+            data: self.data,
+            metric,
+            group_metrics: None,
+            index: Index::BruteForce,
+        }
+    }
+
+    /// Fits a Mahalanobis metric to the rows of every group, where `groups` holds the group
+    /// (`0..ngroups`) of every training row. A group with a single row has no covariance of
+    /// its own and gets the metric fitted to all rows.
+    pub(super) fn group_mahalanobis(
+        mut self,
+        groups: Vec<usize>,
+        ngroups: usize,
+        shrinkage: f64,
+    ) -> Result<Self> {
+        let mut metrics = Vec::with_capacity(ngroups);
+        let mut pooled: Option<Mahalanobis<F>> = None;
+        for group in 0..ngroups {
+            let rows: Vec<usize> = (0..self.data.nrows())
+                .filter(|i| groups[*i] == group)
+                .collect();
+            let metric = if rows.len() > 1 {
+                Mahalanobis::fit(&self.data.select(Axis(0), &rows), shrinkage)?
+            } else {
+                if pooled.is_none() {
+                    pooled = Some(Mahalanobis::fit(self.data.as_ref(), shrinkage)?);
+                }
+                pooled.clone().expect("pooled metric was just fitted")
+            };
+            metrics.push(metric);
+        }
+        self.group_metrics = Some((groups, metrics));
+        self.index = Index::BruteForce;
+        Ok(self)
+    }
+
+    pub(super) fn build_index(&mut self, kind: IndexKind) -> Result<()> {
+        if self.group_metrics.is_some() && kind != IndexKind::BruteForce {
+            return Err(Error::UnsupportedMetric);
+        }
+        self.index = Index::build(kind, &self.data, &self.metric)?;
+        Ok(())
+    }
+
+    pub(super) fn ef_search(&mut self, value: usize) -> Result<()> {
+        match &mut self.index {
+            Index::Hnsw(graph) => {
+                graph.set_ef_search(value);
+                Ok(())
+            }
+            _ => Err(Error::InvalidIndex),
+        }
+    }
+
+    pub(super) fn save_index<W: Write>(&self, mut writer: W) -> Result<()> {
+        match &self.index {
+            Index::Hnsw(graph) => Ok(graph.write(&mut writer)?),
+            _ => Err(Error::InvalidIndex),
+        }
+    }
+
+    pub(super) fn load_index<R: Read>(&mut self, mut reader: R) -> Result<()> {
+        if self.group_metrics.is_some() {
+            return Err(Error::UnsupportedMetric);
+        }
+        let graph = Hnsw::read(&mut reader)?;
+        if graph.len() != self.data.nrows() {
+            return Err(Error::InvalidIndex);
+        }
+        self.index = Index::Hnsw(graph);
+        Ok(())
+    }
+
+    /// Fails unless `points` has as many columns as the training data and no NaN values, or
+    /// if the metric doesn't fit the columns.
+    pub(super) fn check_points(&self, points: ArrayView2<F>) -> Result<()> {
+        self.metric.check_columns(self.data.ncols())?;
+        if points.ncols() != self.data.ncols() {
+            return Err(Error::ShapeMismatch {
+                expected: (points.nrows(), self.data.ncols()),
+                found: points.dim(),
+            });
+        }
+        check_nan(&points)
+    }
+
+    /// Fails unless `point` has as many values as the training data has columns and no NaN
+    /// values, or if the metric doesn't fit the columns.
+    pub(super) fn check_point(&self, point: ArrayView1<F>) -> Result<()> {
+        self.metric.check_columns(self.data.ncols())?;
+        if point.len() != self.data.ncols() {
+            return Err(Error::ShapeMismatch {
+                expected: (1, self.data.ncols()),
+                found: (1, point.len()),
+            });
+        }
+        check_nan(&point.insert_axis(Axis(0)))
+    }
+
+    pub(super) fn recall<S: Data<Elem = F>>(
+        &self,
+        queries: &ArrayBase<S, Ix2>,
+        k: usize,
+    ) -> Result<f64> {
+        self.check_points(queries.view())?;
+        let mut found = 0;
+        let mut total = 0;
+        for point in queries.rows() {
+            let approximate: HashSet<usize> = self
+                .kneighbors(point, k)?
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect();
+            let exact = self.exact_kneighbors(point, k);
+            total += exact.len();
+            found += exact
+                .iter()
+                .filter(|(i, _)| approximate.contains(i))
+                .count();
+        }
+        if total == 0 {
+            return Ok(1.0);
+        }
+        Ok(found as f64 / total as f64)
+    }
+
+    pub(super) fn kneighbors(&self, point: ArrayView1<F>, k: usize) -> Result<Vec<(usize, F)>> {
+        check_k(k, self.data.nrows())?;
+        self.check_point(point)?;
+        if self.group_metrics.is_none() {
+            return Ok(self.index.nearest(&self.data, &self.metric, point, k));
+        }
+        Ok(self.exact_kneighbors(point, k))
+    }
+
+    pub(super) fn kneighbors_many(
+        &self,
+        points: ArrayView2<F>,
+        k: usize,
+    ) -> Result<Vec<Vec<(usize, F)>>> {
+        check_k(k, self.data.nrows())?;
+        self.check_points(points)?;
+        map_indexes(points.nrows(), |i| self.kneighbors(points.row(i), k))
+            .into_iter()
+            .collect()
+    }
+
+    pub(super) fn radius_neighbors(
+        &self,
+        point: ArrayView1<F>,
+        radius: F,
+    ) -> Result<Vec<(usize, F)>> {
+        self.check_point(point)?;
+        let mut neighbours: Vec<(usize, F)> = (0..self.data.nrows())
+            .map(|i| (i, self.distance(point, i)))
+            .filter(|(_, distance)| *distance <= radius)
+            .collect();
+        neighbours.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        Ok(neighbours)
+    }
+
+    /// `kneighbors` by measuring the distance to every training row, whatever the index.
+    fn exact_kneighbors(&self, point: ArrayView1<F>, k: usize) -> Vec<(usize, F)> {
+        let mut candidates = Candidates::new(k);
+        for i in 0..self.data.nrows() {
+            candidates.push(i, self.distance(point, i));
+        }
+        candidates.into_sorted_vec()
+    }
+
+    /// Distance from `point` to training row `i`.
+    fn distance(&self, point: ArrayView1<F>, i: usize) -> F {
+        let row = self.data.row(i);
+        match &self.group_metrics {
+            Some((groups, metrics)) => metrics[groups[i]].distance(point, row),
+            None => self.metric.distance(point, row),
+        }
+    }
+}
+
+/// Fails unless `k` is between one and the number of training rows.
+fn check_k(k: usize, n_samples: usize) -> Result<()> {
+    if k == 0 || k > n_samples {
+        return Err(Error::InvalidK { k, n_samples });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn queries_reject_invalid_k() {
+        let data = Arc::new(array![[0.0, 0.0], [1.0, 0.0], [0.0, 2.0]]);
+        let search = Search::new(data, 3, 1).unwrap();
+        let point = array![0.5, 0.5];
+        assert_eq!(search.kneighbors(point.view(), 3).unwrap().len(), 3);
+        for k in [0, 4] {
+            let invalid = Err(Error::InvalidK { k, n_samples: 3 });
+            assert_eq!(search.kneighbors(point.view(), k), invalid);
+            let points = point.view().insert_axis(Axis(0));
+            assert!(search.kneighbors_many(points, k).is_err());
+        }
+    }
+}
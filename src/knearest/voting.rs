@@ -0,0 +1,106 @@
+use crate::float::Float;
+
+/// How much each of the `k` nearest neighbours counts in the vote or average.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Weights {
+    /// Every neighbour counts the same.
+    #[default]
+    Uniform,
+    /// Neighbours count `1 / distance`. If some neighbours lie exactly on the query point,
+    /// only they vote.
+    InverseDistance,
+    /// Neighbours count `kernel(distance)`; the kernel should return non-negative values.
+    Kernel(fn(f64) -> f64),
+}
+
+/// Which class wins when several classes get the same total weight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The tied class that owns the closest neighbour.
+    #[default]
+    Nearest,
+    /// The tied class that comes first in `KNearest::classes`.
+    FirstClass,
+    /// Drops the farthest neighbour and votes again until the tie is gone.
+    ReduceK,
+}
+
+/// Weight of every neighbour in `nearest`, which holds `(training row, distance)`. Weights
+/// are `f64` whatever the type of the distances.
+pub(super) fn neighbour_weights<F: Float>(weights: Weights, nearest: &[(usize, F)]) -> Vec<f64> {
+    let distances: Vec<f64> = nearest
+        .iter()
+        .map(|(_, distance)| distance.to_f64().expect("Float converts to f64"))
+        .collect();
+    let exact_matches = distances.contains(&0.0);
+    distances
+        .iter()
+        .map(|distance| match weights {
+            Weights::Uniform => 1.0,
+            Weights::InverseDistance if exact_matches => {
+                if *distance == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Weights::InverseDistance => 1.0 / distance,
+            Weights::Kernel(kernel) => kernel(*distance),
+        })
+        .collect()
+}
+
+/// Total weight of every class (by position in `classes`) among `nearest`, where `encoded`
+/// holds the class position of every training row.
+pub(super) fn class_weights<F: Float>(
+    weights: Weights,
+    nearest: &[(usize, F)],
+    encoded: &[usize],
+    nclasses: usize,
+) -> Vec<f64> {
+    let mut totals = vec![0.0; nclasses];
+    for ((i, _), weight) in nearest.iter().zip(neighbour_weights(weights, nearest)) {
+        totals[encoded[*i]] += weight;
+    }
+    totals
+}
+
+/// Position of the winning class among `nearest`, sorted closest first. Classes with a NaN
+/// total (e.g. from a kernel returning NaN) can't win; if every total is NaN, all classes tie.
+pub(super) fn winner<F: Float>(
+    weights: Weights,
+    tie_break: TieBreak,
+    nearest: &[(usize, F)],
+    encoded: &[usize],
+    nclasses: usize,
+) -> usize {
+    let totals = class_weights(weights, nearest, encoded, nclasses);
+    let max = totals
+        .iter()
+        .copied()
+        .filter(|total| !total.is_nan())
+        .fold(f64::NEG_INFINITY, f64::max);
+    let mut tied: Vec<usize> = (0..nclasses).filter(|c| totals[*c] == max).collect();
+    if tied.is_empty() {
+        tied = (0..nclasses).collect();
+    }
+    if tied.len() == 1 {
+        return tied[0];
+    }
+
+    match tie_break {
+        TieBreak::FirstClass => tied[0],
+        TieBreak::ReduceK if nearest.len() > 1 => winner(
+            weights,
+            tie_break,
+            &nearest[..nearest.len() - 1],
+            encoded,
+            nclasses,
+        ),
+        TieBreak::Nearest | TieBreak::ReduceK => nearest
+            .iter()
+            .map(|(i, _)| encoded[*i])
+            .find(|c| tied.contains(c))
+            .unwrap_or(tied[0]),
+    }
+}
@@ -0,0 +1,131 @@
+use super::index::Candidates;
+use crate::distance::Distance;
+use crate::float::Float;
+use ndarray::{Array2, ArrayView1};
+
+enum Node<F> {
+    Leaf {
+        start: usize,
+        end: usize,
+    },
+    /// Rows closer to `vantage` than `radius` (or at that distance) are `inside`, the rest
+    /// `outside`.
+    Vantage {
+        vantage: usize,
+        radius: F,
+        inside: usize,
+        outside: usize,
+    },
+}
+
+/// Vantage-point tree: splits rows by their distance to a chosen training row, so it only
+/// needs the triangle inequality and never looks at individual columns.
+pub(super) struct VpTree<F> {
+    nodes: Vec<Node<F>>,
+    indexes: Vec<usize>,
+}
+
+impl<F: Float> VpTree<F> {
+    pub(super) fn new<D: Distance<F>>(data: &Array2<F>, metric: &D, leaf_size: usize) -> Self {
+        let mut tree = VpTree {
+            nodes: Vec::new(),
+            indexes: (0..data.nrows()).collect(),
+        };
+        tree.build(data, metric, 0, data.nrows(), leaf_size);
+        tree
+    }
+
+    /// Builds the subtree over `indexes[start..end]` and returns its node id.
+    fn build<D: Distance<F>>(
+        &mut self,
+        data: &Array2<F>,
+        metric: &D,
+        start: usize,
+        end: usize,
+        leaf_size: usize,
+    ) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node::Leaf { start, end });
+        if end - start <= leaf_size.max(1) {
+            return id;
+        }
+
+        let vantage = self.indexes[start];
+        let rest = &mut self.indexes[start + 1..end];
+        let mut distances: Vec<(usize, F)> = rest
+            .iter()
+            .map(|i| (*i, metric.distance(data.row(vantage), data.row(*i))))
+            .collect();
+        let mid = distances.len() / 2;
+        distances.select_nth_unstable_by(mid, |a, b| a.1.total_cmp(&b.1));
+        let radius = distances[mid].1;
+        for (slot, (i, _)) in rest.iter_mut().zip(distances) {
+            *slot = i;
+        }
+
+        let split = start + 1 + mid;
+        let inside = self.build(data, metric, start + 1, split, leaf_size);
+        let outside = self.build(data, metric, split, end, leaf_size);
+        self.nodes[id] = Node::Vantage {
+            vantage,
+            radius,
+            inside,
+            outside,
+        };
+        id
+    }
+
+    pub(super) fn nearest<D: Distance<F>>(
+        &self,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        k: usize,
+    ) -> Vec<(usize, F)> {
+        let mut candidates = Candidates::new(k);
+        if !self.indexes.is_empty() {
+            self.search(0, data, metric, point, &mut candidates);
+        }
+        candidates.into_sorted_vec()
+    }
+
+    fn search<D: Distance<F>>(
+        &self,
+        node: usize,
+        data: &Array2<F>,
+        metric: &D,
+        point: ArrayView1<F>,
+        candidates: &mut Candidates<F>,
+    ) {
+        match self.nodes[node] {
+            Node::Leaf { start, end } => {
+                for i in &self.indexes[start..end] {
+                    candidates.push(*i, metric.distance(point, data.row(*i)));
+                }
+            }
+            Node::Vantage {
+                vantage,
+                radius,
+                inside,
+                outside,
+            } => {
+                let distance = metric.distance(point, data.row(vantage));
+                candidates.push(vantage, distance);
+
+                // Triangle inequality: rows inside are at least `distance - radius` away,
+                // rows outside at least `radius - distance`
+                if distance <= radius {
+                    self.search(inside, data, metric, point, candidates);
+                    if radius - distance <= candidates.worst() {
+                        self.search(outside, data, metric, point, candidates);
+                    }
+                } else {
+                    self.search(outside, data, metric, point, candidates);
+                    if distance - radius <= candidates.worst() {
+                        self.search(inside, data, metric, point, candidates);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,30 @@
+pub use distance::Distance;
+pub use error::Error;
+pub use float::Float;
 pub use kmeans::{KMeans, Model};
-pub use knearest::KNearest;
+pub use knearest::{KNearest, KNearestRegressor, Neighbours};
 use ndarray::ArrayView1;
+pub use traits::{Fit, FitTransform, Predict, Transform};
 
+pub mod distance;
+pub mod error;
+pub mod example_utils;
+pub mod float;
 pub mod kmeans;
 pub mod knearest;
-pub mod example_utils;
+mod parallel;
+pub mod preprocessing;
+pub mod traits;
 
-pub fn euclidean_distance(point1: ArrayView1<f64>, point2: ArrayView1<f64>) -> f64 {
-    let mut sum: f64 = 0.0;
-    for i in 0..point1.len() {
-        sum += (point1[i] - point2[i]).powi(2);
-    }
-    sum.sqrt()
+/// Euclidean distance between two points of the same length. Extra values of the longer
+/// point are ignored, so check the lengths first (estimators do this on their inputs); debug
+/// builds assert it.
+pub fn euclidean_distance<F: Float>(point1: ArrayView1<F>, point2: ArrayView1<F>) -> F {
+    debug_assert_eq!(point1.len(), point2.len(), "Points differ in length");
+    point1
+        .iter()
+        .zip(point2)
+        .map(|(a, b)| (*a - *b).powi(2))
+        .sum::<F>()
+        .sqrt()
 }
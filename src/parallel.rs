@@ -0,0 +1,43 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Evaluates `f` for every index in `0..n`, in parallel when the `rayon` feature is enabled.
+/// Results are always returned in index order, so callers that reduce them sequentially get
+/// the same output with or without the feature.
+pub(crate) fn map_indexes<T, F>(n: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        (0..n).into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        (0..n).map(f).collect()
+    }
+}
+
+/// Calls `f` with the index and a mutable reference of every item, in parallel when the
+/// `rayon` feature is enabled.
+pub(crate) fn for_each_mut<T, F>(items: &mut [T], f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut T) + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        items
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, item)| f(i, item));
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        items
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, item)| f(i, item));
+    }
+}
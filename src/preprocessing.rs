@@ -0,0 +1,55 @@
+use crate::error::{check_nan, Error, Result};
+use crate::float::Float;
+use crate::traits::{Fit, Transform};
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+
+/// Scales every column to `0.0..=1.0` using the minimum and maximum seen by `fit`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinMaxScaler;
+
+/// Column ranges learnt by `MinMaxScaler`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FittedMinMaxScaler<F = f64> {
+    min: Array1<F>,
+    max: Array1<F>,
+}
+
+impl<F: Float> FittedMinMaxScaler<F> {
+    pub fn min(&self) -> &Array1<F> {
+        &self.min
+    }
+
+    pub fn max(&self) -> &Array1<F> {
+        &self.max
+    }
+}
+
+impl<F: Float> Fit<F> for MinMaxScaler {
+    type Object = FittedMinMaxScaler<F>;
+
+    /// Fails on an empty input and NaN values.
+    fn fit(&self, data: ArrayView2<F>) -> Result<FittedMinMaxScaler<F>> {
+        if data.nrows() == 0 {
+            return Err(Error::EmptyDataset);
+        }
+        check_nan(&data)?;
+        let min = data.fold_axis(Axis(0), F::infinity(), |acc, x| acc.min(*x));
+        let max = data.fold_axis(Axis(0), F::neg_infinity(), |acc, x| acc.max(*x));
+        Ok(FittedMinMaxScaler { min, max })
+    }
+}
+
+impl<F: Float> Transform<F> for FittedMinMaxScaler<F> {
+    /// Constant columns map to `0.0`. Fails if `data` has a different number of columns than
+    /// the fitted data.
+    fn transform(&self, data: ArrayView2<F>) -> Result<Array2<F>> {
+        if data.ncols() != self.min.len() {
+            return Err(Error::ShapeMismatch {
+                expected: (data.nrows(), self.min.len()),
+                found: data.dim(),
+            });
+        }
+        let span = (&self.max - &self.min).mapv(|x| if x == F::zero() { F::one() } else { x });
+        Ok((&data - &self.min) / &span)
+    }
+}
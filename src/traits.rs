@@ -0,0 +1,38 @@
+use crate::error::Result;
+use crate::float::Float;
+use ndarray::{Array2, ArrayView2};
+
+/// Learns a model from unlabelled samples, one per row of `data`.
+pub trait Fit<F: Float = f64> {
+    type Object;
+
+    fn fit(&self, data: ArrayView2<F>) -> Result<Self::Object>;
+}
+
+/// Produces one prediction per row of `data`.
+pub trait Predict<F: Float = f64> {
+    type Output;
+
+    fn predict(&self, data: ArrayView2<F>) -> Result<Self::Output>;
+}
+
+/// Maps samples to a new feature space, keeping one row per sample.
+pub trait Transform<F: Float = f64> {
+    fn transform(&self, data: ArrayView2<F>) -> Result<Array2<F>>;
+}
+
+/// Fits a model and transforms the same samples with it.
+pub trait FitTransform<F: Float = f64>: Fit<F> {
+    fn fit_transform(&self, data: ArrayView2<F>) -> Result<(Self::Object, Array2<F>)>;
+}
+
+impl<F: Float, T: Fit<F>> FitTransform<F> for T
+where
+    T::Object: Transform<F>,
+{
+    fn fit_transform(&self, data: ArrayView2<F>) -> Result<(Self::Object, Array2<F>)> {
+        let model = self.fit(data)?;
+        let transformed = model.transform(data)?;
+        Ok((model, transformed))
+    }
+}